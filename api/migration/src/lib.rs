@@ -1,12 +1,70 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261019_000001_add_soft_delete;
+mod m20261019_000002_create_data_exports;
+mod m20261019_000003_add_timestamps;
+mod m20261019_000004_create_audit_events;
+mod m20261019_000005_add_todo_fields;
+mod m20261019_000006_add_recurrence;
+mod m20261019_000007_add_post_parent;
+mod m20261019_000008_create_tags;
+mod m20261019_000009_add_post_search;
+mod m20261019_000010_create_comments;
+mod m20261019_000011_create_reactions;
+mod m20261019_000012_unique_post_titles;
+mod m20261019_000013_add_post_visibility;
+mod m20261019_000014_create_post_shares;
+mod m20261019_000015_create_organizations;
+mod m20261019_000016_create_post_revisions;
+mod m20261019_000017_add_versions;
+mod m20261019_000018_create_idempotency_keys;
+mod m20261019_000019_add_post_drafts;
+mod m20261019_000020_add_content_format;
+mod m20261019_000021_create_attachments;
+mod m20261019_000022_create_attachment_variants;
+mod m20261019_000023_create_quotas;
+mod m20261019_000024_add_org_public;
+mod m20261019_000025_add_revision_content_format;
+mod m20261019_000026_purge_idempotency_keys;
+mod m20261019_000027_withhold_unstripped_images;
+mod m20261019_000028_add_membership_acceptance;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261019_000001_add_soft_delete::Migration),
+            Box::new(m20261019_000002_create_data_exports::Migration),
+            Box::new(m20261019_000003_add_timestamps::Migration),
+            Box::new(m20261019_000004_create_audit_events::Migration),
+            Box::new(m20261019_000005_add_todo_fields::Migration),
+            Box::new(m20261019_000006_add_recurrence::Migration),
+            Box::new(m20261019_000007_add_post_parent::Migration),
+            Box::new(m20261019_000008_create_tags::Migration),
+            Box::new(m20261019_000009_add_post_search::Migration),
+            Box::new(m20261019_000010_create_comments::Migration),
+            Box::new(m20261019_000011_create_reactions::Migration),
+            Box::new(m20261019_000012_unique_post_titles::Migration),
+            Box::new(m20261019_000013_add_post_visibility::Migration),
+            Box::new(m20261019_000014_create_post_shares::Migration),
+            Box::new(m20261019_000015_create_organizations::Migration),
+            Box::new(m20261019_000016_create_post_revisions::Migration),
+            Box::new(m20261019_000017_add_versions::Migration),
+            Box::new(m20261019_000018_create_idempotency_keys::Migration),
+            Box::new(m20261019_000019_add_post_drafts::Migration),
+            Box::new(m20261019_000020_add_content_format::Migration),
+            Box::new(m20261019_000021_create_attachments::Migration),
+            Box::new(m20261019_000022_create_attachment_variants::Migration),
+            Box::new(m20261019_000023_create_quotas::Migration),
+            Box::new(m20261019_000024_add_org_public::Migration),
+            Box::new(m20261019_000025_add_revision_content_format::Migration),
+            Box::new(m20261019_000026_purge_idempotency_keys::Migration),
+            Box::new(m20261019_000027_withhold_unstripped_images::Migration),
+            Box::new(m20261019_000028_add_membership_acceptance::Migration),
+        ]
     }
 }
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    integer, pk_auto, string, string_null, timestamp_with_time_zone, timestamp_with_time_zone_null,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Personal data export jobs and their download links
+        manager
+            .create_table(
+                Table::create()
+                    .table(DataExports::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DataExports::Id))
+                    .col(integer(DataExports::UserId))
+                    .col(string(DataExports::Status))
+                    .col(string_null(DataExports::FilePath))
+                    .col(string_null(DataExports::DownloadToken).unique_key())
+                    .col(timestamp_with_time_zone_null(DataExports::ExpiresAt))
+                    .col(
+                        timestamp_with_time_zone(DataExports::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_data_exports_user")
+                            .from(DataExports::Table, DataExports::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DataExports::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum DataExports {
+    Table,
+    Id,
+    UserId,
+    Status,
+    FilePath,
+    DownloadToken,
+    ExpiresAt,
+    CreatedAt,
+}
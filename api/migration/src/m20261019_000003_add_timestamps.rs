@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{integer_null, timestamp_with_time_zone};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // DEFAULT now() also backfills rows that already exist
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(
+                        timestamp_with_time_zone(Users::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_column(
+                        timestamp_with_time_zone(Users::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_column(integer_null(Users::CreatedBy))
+                    .add_column(integer_null(Users::UpdatedBy))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(
+                        timestamp_with_time_zone(Posts::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_column(
+                        timestamp_with_time_zone(Posts::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_column(integer_null(Posts::CreatedBy))
+                    .add_column(integer_null(Posts::UpdatedBy))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing posts were all written by their owner
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Posts::Table)
+                    .value(Posts::CreatedBy, Expr::col(Posts::UserId))
+                    .value(Posts::UpdatedBy, Expr::col(Posts::UserId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Listing posts by recency
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_user_created_at")
+                    .table(Posts::Table)
+                    .col(Posts::UserId)
+                    .col(Posts::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_posts_user_created_at")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::CreatedAt)
+                    .drop_column(Posts::UpdatedAt)
+                    .drop_column(Posts::CreatedBy)
+                    .drop_column(Posts::UpdatedBy)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::CreatedAt)
+                    .drop_column(Users::UpdatedAt)
+                    .drop_column(Users::CreatedBy)
+                    .drop_column(Users::UpdatedBy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    CreatedAt,
+    UpdatedAt,
+    CreatedBy,
+    UpdatedBy,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    UserId,
+    CreatedAt,
+    UpdatedAt,
+    CreatedBy,
+    UpdatedBy,
+}
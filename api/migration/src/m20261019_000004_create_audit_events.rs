@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    integer_null, json_binary_null, pk_auto, string, string_null, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign keys on purpose: events must outlive the accounts they describe
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditEvents::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AuditEvents::Id))
+                    .col(string(AuditEvents::EventType))
+                    .col(integer_null(AuditEvents::ActorId))
+                    .col(string(AuditEvents::TargetType))
+                    .col(integer_null(AuditEvents::TargetId))
+                    .col(string_null(AuditEvents::Ip))
+                    .col(string_null(AuditEvents::UserAgent))
+                    .col(json_binary_null(AuditEvents::Changes))
+                    .col(
+                        timestamp_with_time_zone(AuditEvents::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_events_actor_created_at")
+                    .table(AuditEvents::Table)
+                    .col(AuditEvents::ActorId)
+                    .col(AuditEvents::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_events_type_created_at")
+                    .table(AuditEvents::Table)
+                    .col(AuditEvents::EventType)
+                    .col(AuditEvents::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Role decides who can read the audit log
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .add_column(string(Users::Role).default("user"))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Users::Table)
+                    .drop_column(Users::Role)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(AuditEvents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditEvents {
+    Table,
+    Id,
+    EventType,
+    ActorId,
+    TargetType,
+    TargetId,
+    Ip,
+    UserAgent,
+    Changes,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Role,
+}
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{boolean, integer, string, timestamp_with_time_zone_null};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(boolean(Posts::Done).default(false))
+                    .add_column(timestamp_with_time_zone_null(Posts::CompletedAt))
+                    .add_column(timestamp_with_time_zone_null(Posts::DueAt))
+                    // 0 = low, 1 = normal, 2 = high, 3 = urgent (sortable)
+                    .add_column(integer(Posts::Priority).default(1))
+                    .add_column(string(Posts::Status).default("open"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Overdue / due-soon listings
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_user_due_at")
+                    .table(Posts::Table)
+                    .col(Posts::UserId)
+                    .col(Posts::DueAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_posts_user_due_at")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::Done)
+                    .drop_column(Posts::CompletedAt)
+                    .drop_column(Posts::DueAt)
+                    .drop_column(Posts::Priority)
+                    .drop_column(Posts::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    UserId,
+    Done,
+    CompletedAt,
+    DueAt,
+    Priority,
+    Status,
+}
@@ -0,0 +1,63 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{integer_null, text_null, timestamp_with_time_zone_null};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // series_id groups the occurrences of one recurring todo (id of the first one);
+        // recurrence_start is the DTSTART the RRULE is evaluated from
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(text_null(Posts::Rrule))
+                    .add_column(integer_null(Posts::SeriesId))
+                    .add_column(timestamp_with_time_zone_null(Posts::RecurrenceStart))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_series_id")
+                    .table(Posts::Table)
+                    .col(Posts::SeriesId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_posts_series_id")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::Rrule)
+                    .drop_column(Posts::SeriesId)
+                    .drop_column(Posts::RecurrenceStart)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Rrule,
+    SeriesId,
+    RecurrenceStart,
+}
@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Full-text search: the vector is maintained by PostgreSQL itself, title ranks above content.
+        // The 'simple' configuration does no stemming, so it works for any language.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "ALTER TABLE posts ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+                setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+                setweight(to_tsvector('simple', coalesce(content, '')), 'B')
+            ) STORED",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE INDEX idx_posts_search_vector ON posts USING GIN (search_vector)",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_posts_search_vector")
+            .await?;
+        db.execute_unprepared("ALTER TABLE posts DROP COLUMN IF EXISTS search_vector")
+            .await?;
+        Ok(())
+    }
+}
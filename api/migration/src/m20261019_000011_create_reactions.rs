@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{integer, string_len, timestamp_with_time_zone};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One reaction of each kind per user and post
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reactions::Table)
+                    .if_not_exists()
+                    .col(integer(Reactions::PostId))
+                    .col(integer(Reactions::UserId))
+                    .col(string_len(Reactions::Kind, 16))
+                    .col(
+                        timestamp_with_time_zone(Reactions::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(Reactions::PostId)
+                            .col(Reactions::UserId)
+                            .col(Reactions::Kind),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_reactions_post")
+                            .from(Reactions::Table, Reactions::PostId)
+                            .to(Posts::Table, Posts::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_reactions_user")
+                            .from(Reactions::Table, Reactions::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // "Posts I reacted to"
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reactions_user_id")
+                    .table(Reactions::Table)
+                    .col(Reactions::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reactions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Reactions {
+    Table,
+    PostId,
+    UserId,
+    Kind,
+    CreatedAt,
+}
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    integer, pk_auto, string_len, timestamp_with_time_zone, timestamp_with_time_zone_null,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A grant of viewer/editor access to a post; pending until the invitee accepts it
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostShares::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PostShares::Id))
+                    .col(integer(PostShares::PostId))
+                    .col(integer(PostShares::UserId))
+                    .col(string_len(PostShares::Role, 16))
+                    .col(integer(PostShares::InvitedBy))
+                    .col(timestamp_with_time_zone_null(PostShares::AcceptedAt))
+                    .col(
+                        timestamp_with_time_zone(PostShares::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_post_shares_post")
+                            .from(PostShares::Table, PostShares::PostId)
+                            .to(Posts::Table, Posts::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_post_shares_user")
+                            .from(PostShares::Table, PostShares::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_post_shares_invited_by")
+                            .from(PostShares::Table, PostShares::InvitedBy)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_shares_post_user")
+                    .table(PostShares::Table)
+                    .col(PostShares::PostId)
+                    .col(PostShares::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_shares_user_id")
+                    .table(PostShares::Table)
+                    .col(PostShares::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostShares::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum PostShares {
+    Table,
+    Id,
+    PostId,
+    UserId,
+    Role,
+    InvitedBy,
+    AcceptedAt,
+    CreatedAt,
+}
@@ -0,0 +1,178 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    integer, integer_null, pk_auto, string, string_len, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Organizations::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Organizations::Id))
+                    .col(string(Organizations::Name))
+                    .col(integer(Organizations::CreatedBy))
+                    .col(
+                        timestamp_with_time_zone(Organizations::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrgMemberships::Table)
+                    .if_not_exists()
+                    .col(integer(OrgMemberships::OrgId))
+                    .col(integer(OrgMemberships::UserId))
+                    .col(string_len(OrgMemberships::Role, 16))
+                    .col(
+                        timestamp_with_time_zone(OrgMemberships::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(OrgMemberships::OrgId)
+                            .col(OrgMemberships::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_org_memberships_org")
+                            .from(OrgMemberships::Table, OrgMemberships::OrgId)
+                            .to(Organizations::Table, Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_org_memberships_user")
+                            .from(OrgMemberships::Table, OrgMemberships::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_org_memberships_user_id")
+                    .table(OrgMemberships::Table)
+                    .col(OrgMemberships::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(integer_null(Posts::OrgId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every existing user gets a personal workspace that owns their posts
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "INSERT INTO organizations (name, created_by)
+                SELECT name || '''s workspace', id FROM users ORDER BY id",
+        )
+        .await?;
+        db.execute_unprepared(
+            "INSERT INTO org_memberships (org_id, user_id, role)
+                SELECT id, created_by, 'owner' FROM organizations",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE posts SET org_id = organizations.id
+                FROM organizations WHERE organizations.created_by = posts.user_id",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .modify_column(integer(Posts::OrgId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_posts_org")
+                            .from_tbl(Posts::Table)
+                            .from_col(Posts::OrgId)
+                            .to_tbl(Organizations::Table)
+                            .to_col(Organizations::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_org_id")
+                    .table(Posts::Table)
+                    .col(Posts::OrgId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_foreign_key(Alias::new("fk_posts_org"))
+                    .drop_column(Posts::OrgId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(OrgMemberships::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Organizations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    OrgId,
+}
+
+#[derive(DeriveIden)]
+enum Organizations {
+    Table,
+    Id,
+    Name,
+    CreatedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OrgMemberships {
+    Table,
+    OrgId,
+    UserId,
+    Role,
+    CreatedAt,
+}
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    integer, integer_null, pk_auto, string, text, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostRevisions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PostRevisions::Id))
+                    .col(integer(PostRevisions::PostId))
+                    .col(integer(PostRevisions::Revision))
+                    .col(string(PostRevisions::Title))
+                    .col(text(PostRevisions::Content))
+                    .col(integer_null(PostRevisions::EditedBy))
+                    .col(
+                        timestamp_with_time_zone(PostRevisions::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_post_revisions_post")
+                            .from(PostRevisions::Table, PostRevisions::PostId)
+                            .to(Posts::Table, Posts::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Revisions are numbered 1, 2, ... within each post
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_revisions_post_revision")
+                    .table(PostRevisions::Table)
+                    .col(PostRevisions::PostId)
+                    .col(PostRevisions::Revision)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The current content of existing posts becomes their first revision
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO post_revisions (post_id, revision, title, content, edited_by, created_at)
+                    SELECT id, 1, title, content, updated_by, updated_at FROM posts",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostRevisions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum PostRevisions {
+    Table,
+    Id,
+    PostId,
+    Revision,
+    Title,
+    Content,
+    EditedBy,
+    CreatedAt,
+}
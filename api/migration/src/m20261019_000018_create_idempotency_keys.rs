@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    blob_null, integer_null, json_binary_null, pk_auto, string_len, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // First response to each `Idempotency-Key`, replayed to retries of the same request
+        manager
+            .create_table(
+                Table::create()
+                    .table(IdempotencyKeys::Table)
+                    .if_not_exists()
+                    .col(pk_auto(IdempotencyKeys::Id))
+                    .col(string_len(IdempotencyKeys::Scope, 64))
+                    .col(string_len(IdempotencyKeys::Key, 255))
+                    .col(string_len(IdempotencyKeys::RequestHash, 64))
+                    // NULL while the first request is still being processed
+                    .col(integer_null(IdempotencyKeys::StatusCode))
+                    .col(json_binary_null(IdempotencyKeys::ResponseHeaders))
+                    .col(blob_null(IdempotencyKeys::ResponseBody))
+                    .col(
+                        timestamp_with_time_zone(IdempotencyKeys::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(timestamp_with_time_zone(IdempotencyKeys::ExpiresAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_idempotency_keys_scope_key")
+                    .table(IdempotencyKeys::Table)
+                    .col(IdempotencyKeys::Scope)
+                    .col(IdempotencyKeys::Key)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_idempotency_keys_expires_at")
+                    .table(IdempotencyKeys::Table)
+                    .col(IdempotencyKeys::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IdempotencyKeys::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum IdempotencyKeys {
+    Table,
+    Id,
+    Scope,
+    Key,
+    RequestHash,
+    StatusCode,
+    ResponseHeaders,
+    ResponseBody,
+    CreatedAt,
+    ExpiresAt,
+}
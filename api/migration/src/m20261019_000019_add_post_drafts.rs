@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{boolean, timestamp_with_time_zone_null};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Drafts stay hidden from everyone but their author until published,
+        // either by hand or by the scheduler once `publish_at` passes
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .add_column(boolean(Posts::Draft).default(false))
+                    .add_column(timestamp_with_time_zone_null(Posts::PublishAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // The scheduler looks up drafts that are due
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_draft_publish_at")
+                    .table(Posts::Table)
+                    .col(Posts::Draft)
+                    .col(Posts::PublishAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_posts_draft_publish_at")
+                    .table(Posts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Posts::Table)
+                    .drop_column(Posts::Draft)
+                    .drop_column(Posts::PublishAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Draft,
+    PublishAt,
+}
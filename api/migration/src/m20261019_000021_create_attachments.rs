@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    big_integer, integer, pk_auto, string_len, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // File metadata only; the bytes live in the configured storage backend under
+        // `storage_key`. Rows go away with their post or uploader (like comments).
+        manager
+            .create_table(
+                Table::create()
+                    .table(Attachments::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Attachments::Id))
+                    .col(integer(Attachments::PostId))
+                    .col(integer(Attachments::UserId))
+                    .col(string_len(Attachments::FileName, 255))
+                    .col(string_len(Attachments::ContentType, 255))
+                    .col(big_integer(Attachments::SizeBytes))
+                    .col(string_len(Attachments::StorageKey, 255).unique_key())
+                    .col(
+                        timestamp_with_time_zone(Attachments::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_attachments_post")
+                            .from(Attachments::Table, Attachments::PostId)
+                            .to(Posts::Table, Posts::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_attachments_user")
+                            .from(Attachments::Table, Attachments::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_attachments_post_id")
+                    .table(Attachments::Table)
+                    .col(Attachments::PostId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Attachments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Posts {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Attachments {
+    Table,
+    Id,
+    PostId,
+    UserId,
+    FileName,
+    ContentType,
+    SizeBytes,
+    StorageKey,
+    CreatedAt,
+}
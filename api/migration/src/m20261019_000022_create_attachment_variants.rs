@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    big_integer, integer, pk_auto, string_len, string_len_null, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Progress of the background thumbnail job: NULL for files that are not images,
+        // 'pending' until processed, then 'ready' or 'failed'
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Attachments::Table)
+                    .add_column(string_len_null(Attachments::VariantsStatus, 16))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_attachments_variants_status")
+                    .table(Attachments::Table)
+                    .col(Attachments::VariantsStatus)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Resized copies of an image attachment, stored next to the original
+        manager
+            .create_table(
+                Table::create()
+                    .table(AttachmentVariants::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AttachmentVariants::Id))
+                    .col(integer(AttachmentVariants::AttachmentId))
+                    .col(string_len(AttachmentVariants::Variant, 32))
+                    .col(string_len(AttachmentVariants::ContentType, 255))
+                    .col(integer(AttachmentVariants::Width))
+                    .col(integer(AttachmentVariants::Height))
+                    .col(big_integer(AttachmentVariants::SizeBytes))
+                    .col(string_len(AttachmentVariants::StorageKey, 255).unique_key())
+                    .col(
+                        timestamp_with_time_zone(AttachmentVariants::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_attachment_variants_attachment")
+                            .from(AttachmentVariants::Table, AttachmentVariants::AttachmentId)
+                            .to(Attachments::Table, Attachments::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_attachment_variants_attachment_variant")
+                    .table(AttachmentVariants::Table)
+                    .col(AttachmentVariants::AttachmentId)
+                    .col(AttachmentVariants::Variant)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AttachmentVariants::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_attachments_variants_status")
+                    .table(Attachments::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Attachments::Table)
+                    .drop_column(Attachments::VariantsStatus)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Attachments {
+    Table,
+    Id,
+    VariantsStatus,
+}
+
+#[derive(DeriveIden)]
+enum AttachmentVariants {
+    Table,
+    Id,
+    AttachmentId,
+    Variant,
+    ContentType,
+    Width,
+    Height,
+    SizeBytes,
+    StorageKey,
+    CreatedAt,
+}
@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::{
+    big_integer_null, date, integer, integer_null, timestamp_with_time_zone,
+};
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Per-user overrides of the role quotas from the configuration; NULL keeps the role default
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserQuotas::Table)
+                    .if_not_exists()
+                    .col(integer(UserQuotas::UserId).primary_key())
+                    .col(integer_null(UserQuotas::MaxPosts))
+                    .col(big_integer_null(UserQuotas::MaxAttachmentBytes))
+                    .col(integer_null(UserQuotas::MaxRequestsPerDay))
+                    .col(
+                        timestamp_with_time_zone(UserQuotas::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_user_quotas_user")
+                            .from(UserQuotas::Table, UserQuotas::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Authenticated requests per user and UTC day, for the daily request quota
+        manager
+            .create_table(
+                Table::create()
+                    .table(RequestCounts::Table)
+                    .if_not_exists()
+                    .col(integer(RequestCounts::UserId))
+                    .col(date(RequestCounts::Day))
+                    .col(integer(RequestCounts::Count).default(0))
+                    .primary_key(
+                        Index::create()
+                            .col(RequestCounts::UserId)
+                            .col(RequestCounts::Day),
+                    )
+                    .foreign_key(
+                        ForeignKeyCreateStatement::new()
+                            .name("fk_request_counts_user")
+                            .from(RequestCounts::Table, RequestCounts::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Usage sums the attachment bytes of each uploader
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_attachments_user_id")
+                    .table(Attachments::Table)
+                    .col(Attachments::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_attachments_user_id")
+                    .table(Attachments::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RequestCounts::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserQuotas::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Attachments {
+    Table,
+    UserId,
+}
+
+#[derive(DeriveIden)]
+enum UserQuotas {
+    Table,
+    UserId,
+    MaxPosts,
+    MaxAttachmentBytes,
+    MaxRequestsPerDay,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum RequestCounts {
+    Table,
+    UserId,
+    Day,
+    Count,
+}
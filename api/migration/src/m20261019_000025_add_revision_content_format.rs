@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::string_len;
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A restored revision brings back its format along with the text
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PostRevisions::Table)
+                    .add_column(string_len(PostRevisions::ContentFormat, 16).default("plain"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Older revisions did not record their format; the post's current one is the best guess
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE post_revisions SET content_format = posts.content_format
+                    FROM posts WHERE posts.id = post_revisions.post_id",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PostRevisions::Table)
+                    .drop_column(PostRevisions::ContentFormat)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostRevisions {
+    Table,
+    ContentFormat,
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Stored keys may hold plain SHA-256 hashes of login bodies and session cookies;
+        // they are only a short-lived replay cache, so drop them all
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM idempotency_keys")
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Images in formats we cannot strip (HEIC, TIFF, ...) were stored with their metadata;
+        // marking them failed keeps their originals from being downloaded
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE attachments SET variants_status = 'failed'
+                    WHERE variants_status IS NULL AND content_type LIKE 'image/%'
+                        AND content_type NOT IN ('image/jpeg', 'image/png', 'image/gif', 'image/webp')",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
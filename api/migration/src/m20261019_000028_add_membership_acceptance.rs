@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::schema::timestamp_with_time_zone_null;
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Adding a member is an invitation; until the user accepts it the membership grants nothing.
+        // Existing memberships count as accepted when they were created.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OrgMemberships::Table)
+                    .add_column(timestamp_with_time_zone_null(OrgMemberships::AcceptedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("UPDATE org_memberships SET accepted_at = created_at")
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM org_memberships WHERE accepted_at IS NULL")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OrgMemberships::Table)
+                    .drop_column(OrgMemberships::AcceptedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrgMemberships {
+    Table,
+    AcceptedAt,
+}
@@ -0,0 +1,209 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::config::attachment_url_ttl;
+use crate::jwt::derived_secret;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum VariantsStatus {
+    // Czeka na zadanie w tle generujące miniatury
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "ready")]
+    Ready,
+    // Pliku nie dało się odczytać jako obrazka albo oczyścić z metadanych — oryginału
+    // nie wydajemy, bo mógłby zdradzić np. położenie z EXIF
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+// Najdłuższa nazwa pliku, jaką zapisujemy (kolumna ma 255 znaków)
+const MAX_FILE_NAME_LEN: usize = 255;
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "attachments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub post_id: i32,
+    // Kto wgrał plik
+    pub user_id: i32,
+    pub file_name: String,
+    // Typ rozpoznany z zawartości pliku, nie z nagłówka klienta
+    pub content_type: String,
+    pub size_bytes: i64,
+    // Klucz obiektu w magazynie plików
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    // Miniatury — tylko dla obrazków (dla innych plików brak)
+    pub variants_status: Option<VariantsStatus>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::post::Entity",
+        from = "Column::PostId",
+        to = "super::post::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Post,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(has_many = "super::attachment_variant::Entity")]
+    AttachmentVariant,
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::attachment_variant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AttachmentVariant.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Załącznik w odpowiedziach API razem z podpisanymi linkami do pobrania
+#[derive(Serialize)]
+pub struct AttachmentResponse {
+    #[serde(flatten)]
+    pub attachment: Model,
+    pub download_url: String,
+    pub variants: Vec<VariantResponse>,
+}
+
+#[derive(Serialize)]
+pub struct VariantResponse {
+    #[serde(flatten)]
+    pub variant: super::attachment_variant::Model,
+    pub url: String,
+}
+
+impl AttachmentResponse {
+    pub fn new(attachment: Model, variants: Vec<super::attachment_variant::Model>) -> Self {
+        AttachmentResponse {
+            download_url: download_url(attachment.id, None),
+            variants: variants
+                .into_iter()
+                .map(|variant| VariantResponse {
+                    url: download_url(attachment.id, Some(&variant.variant)),
+                    variant,
+                })
+                .collect(),
+            attachment,
+        }
+    }
+}
+
+// `/attachments/{id}/download?variant=thumb&expires=…&signature=…`
+#[derive(Deserialize)]
+pub struct DownloadQuery {
+    // Brak — oryginalny plik
+    pub variant: Option<String>,
+    pub expires: i64,
+    pub signature: String,
+}
+
+// Klucz podpisu linków jest wyprowadzony z sekretu JWT z własną etykietą, więc podpis linku
+// nie ma nic wspólnego z podpisem tokena
+fn signer(attachment_id: i32, variant: Option<&str>, expires: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&derived_secret("attachment-download"))
+        .expect("HMAC accepts keys of any length");
+    mac.update(
+        format!(
+            "{}:{}:{}",
+            attachment_id,
+            variant.unwrap_or_default(),
+            expires
+        )
+        .as_bytes(),
+    );
+    mac
+}
+
+// Link ważny przez `ATTACHMENT_URL_TTL_MINUTES` — można go wkleić w <img> albo przekazać dalej
+pub fn download_url(attachment_id: i32, variant: Option<&str>) -> String {
+    let expires = (Utc::now() + attachment_url_ttl()).timestamp();
+    let signature = hex::encode(
+        signer(attachment_id, variant, expires)
+            .finalize()
+            .into_bytes(),
+    );
+    match variant {
+        Some(variant) => format!(
+            "/attachments/{}/download?variant={}&expires={}&signature={}",
+            attachment_id, variant, expires, signature
+        ),
+        None => format!(
+            "/attachments/{}/download?expires={}&signature={}",
+            attachment_id, expires, signature
+        ),
+    }
+}
+
+// Podpis pasuje (porównanie w stałym czasie) i link jeszcze nie wygasł
+pub fn verify_download(attachment_id: i32, query: &DownloadQuery) -> bool {
+    if query.expires < Utc::now().timestamp() {
+        return false;
+    }
+    match hex::decode(&query.signature) {
+        Ok(signature) => signer(attachment_id, query.variant.as_deref(), query.expires)
+            .verify_slice(&signature)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Typ pliku rozpoznany po sygnaturze; tekst bez sygnatury to text/plain
+pub fn sniff_content_type(data: &[u8]) -> String {
+    match infer::get(data) {
+        Some(kind) => kind.mime_type().to_owned(),
+        None if std::str::from_utf8(data).is_ok() => String::from("text/plain"),
+        None => String::from("application/octet-stream"),
+    }
+}
+
+// Pliki wykonywalne (exe, elf, mach-o…) odrzucamy niezależnie od nazwy
+pub fn is_executable(data: &[u8]) -> bool {
+    infer::get(data).is_some_and(|kind| kind.matcher_type() == infer::MatcherType::App)
+}
+
+// Sama nazwa pliku — bez katalogów i znaków sterujących, przycięta do długości kolumny
+pub fn clean_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect::<String>()
+        .trim()
+        .chars()
+        .take(MAX_FILE_NAME_LEN)
+        .collect();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        String::from("file")
+    } else {
+        cleaned
+    }
+}
@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+// Pomniejszona kopia obrazka-załącznika (np. "thumb"), bez metadanych EXIF
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "attachment_variants")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_serializing)]
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub attachment_id: i32,
+    pub variant: String,
+    pub content_type: String,
+    pub width: i32,
+    pub height: i32,
+    pub size_bytes: i64,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    #[serde(skip_serializing)]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::attachment::Entity",
+        from = "Column::AttachmentId",
+        to = "super::attachment::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Attachment,
+}
+
+impl Related<super::attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
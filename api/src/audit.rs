@@ -0,0 +1,166 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+use chrono::Utc;
+use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::Set;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::trusted_proxies;
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+pub enum EventType {
+    #[sea_orm(string_value = "user.register")]
+    #[serde(rename = "user.register")]
+    UserRegister,
+    #[sea_orm(string_value = "user.login")]
+    #[serde(rename = "user.login")]
+    UserLogin,
+    #[sea_orm(string_value = "user.login_failed")]
+    #[serde(rename = "user.login_failed")]
+    UserLoginFailed,
+    #[sea_orm(string_value = "user.update")]
+    #[serde(rename = "user.update")]
+    UserUpdate,
+    #[sea_orm(string_value = "user.delete")]
+    #[serde(rename = "user.delete")]
+    UserDelete,
+    #[sea_orm(string_value = "user.restore")]
+    #[serde(rename = "user.restore")]
+    UserRestore,
+    #[sea_orm(string_value = "post.create")]
+    #[serde(rename = "post.create")]
+    PostCreate,
+    #[sea_orm(string_value = "post.update")]
+    #[serde(rename = "post.update")]
+    PostUpdate,
+    #[sea_orm(string_value = "post.delete")]
+    #[serde(rename = "post.delete")]
+    PostDelete,
+    #[sea_orm(string_value = "post.restore")]
+    #[serde(rename = "post.restore")]
+    PostRestore,
+    #[sea_orm(string_value = "user.quota_update")]
+    #[serde(rename = "user.quota_update")]
+    UserQuotaUpdate,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "audit_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub event_type: EventType,
+    pub actor_id: Option<i32>,
+    pub target_type: String,
+    pub target_id: Option<i32>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub changes: Option<Json>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Zdarzenie do zapisania — IP i User-Agent bierzemy z żądania
+pub struct NewEvent {
+    pub event_type: EventType,
+    pub actor_id: Option<i32>,
+    pub target_type: &'static str,
+    pub target_id: Option<i32>,
+    pub changes: Option<Value>,
+}
+
+// Adres klienta: adres połączenia, chyba że to zaufane proxy — wtedy idziemy od prawej
+// po X-Forwarded-For i bierzemy pierwszy adres spoza proxy (wcześniejsze wpisy podaje klient)
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    let trusted = trusted_proxies();
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<IpAddr> = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+    forwarded
+        .iter()
+        .rev()
+        .find(|ip| !trusted.contains(ip))
+        .or(forwarded.first())
+        .copied()
+        .or(Some(peer))
+}
+
+// Zapisz zdarzenie; błąd zapisu logujemy, ale nie przerywamy obsługi żądania
+pub async fn record(db: &DbConn, req: &HttpRequest, event: NewEvent) {
+    let ip = client_ip(req).map(|ip| ip.to_string());
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_owned);
+
+    let row = ActiveModel {
+        event_type: Set(event.event_type),
+        actor_id: Set(event.actor_id),
+        target_type: Set(event.target_type.to_owned()),
+        target_id: Set(event.target_id),
+        ip: Set(ip),
+        user_agent: Set(user_agent),
+        changes: Set(event.changes),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+
+    if let Err(e) = row.insert(db).await {
+        eprintln!("Failed to record audit event: {}", e);
+    }
+}
+
+// Różnica między dwoma zserializowanymi modelami: {"pole": {"from": .., "to": ..}}.
+// Pola pomijane przy serializacji (np. hash hasła) nigdy tu nie trafiają.
+pub fn diff<B: Serialize, A: Serialize>(before: Option<&B>, after: &A) -> Option<Value> {
+    let before = before
+        .and_then(|b| serde_json::to_value(b).ok())
+        .unwrap_or(Value::Null);
+    let after = serde_json::to_value(after).ok()?;
+
+    let mut changes = Map::new();
+    if let Value::Object(fields) = after {
+        for (key, new) in fields {
+            // Znaczniki czasu zmieniają się przy każdym zapisie — to nie jest informacja
+            if key == "updated_at" || key == "updated_by" {
+                continue;
+            }
+            let old = before.get(&key).cloned().unwrap_or(Value::Null);
+            if old != new {
+                changes.insert(key, serde_json::json!({ "from": old, "to": new }));
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        None
+    } else {
+        Some(Value::Object(changes))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub actor_id: Option<i32>,
+    pub event_type: Option<EventType>,
+    pub from: Option<DateTimeWithTimeZone>,
+    pub to: Option<DateTimeWithTimeZone>,
+    pub limit: Option<u64>,
+}
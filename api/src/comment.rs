@@ -0,0 +1,124 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::Set;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+pub struct CommentCreate {
+    pub content: String,
+    // Odpowiedź na inny komentarz tego samego posta
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct CommentUpdate {
+    pub content: String,
+}
+
+// Komentarz razem z odpowiedziami
+#[derive(Serialize)]
+pub struct CommentThread {
+    #[serde(flatten)]
+    pub comment: Model,
+    pub replies: Vec<CommentThread>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "comments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
+    pub parent_id: Option<i32>,
+    pub content: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::post::Entity",
+        from = "Column::PostId",
+        to = "super::post::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Post,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id",
+        on_delete = "Cascade"
+    )]
+    Parent,
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+// Znaczniki czasu ustawiane automatycznie przy każdym zapisie
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        if insert {
+            self.created_at = Set(now);
+        }
+        self.updated_at = Set(now);
+        Ok(self)
+    }
+}
+
+// Ułóż komentarze posta w wątki; najstarsze najpierw na każdym poziomie
+pub fn build_threads(mut comments: Vec<Model>) -> Vec<CommentThread> {
+    comments.sort_by_key(|c| (c.created_at, c.id));
+
+    let mut replies: HashMap<i32, Vec<Model>> = HashMap::new();
+    let mut roots = vec![];
+    for comment in comments {
+        match comment.parent_id {
+            Some(parent_id) => replies.entry(parent_id).or_default().push(comment),
+            None => roots.push(comment),
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|root| build_node(root, &mut replies))
+        .collect()
+}
+
+fn build_node(comment: Model, replies: &mut HashMap<i32, Vec<Model>>) -> CommentThread {
+    let children = replies
+        .remove(&comment.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|reply| build_node(reply, replies))
+        .collect();
+
+    CommentThread {
+        comment,
+        replies: children,
+    }
+}
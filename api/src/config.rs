@@ -0,0 +1,122 @@
+use chrono::Duration;
+use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::quota::{limit, Limits};
+use crate::search::SearchBackend;
+use crate::storage::{S3Config, StorageBackend};
+use crate::user::Role;
+
+// Odczytaj zmienną środowiskową, a jeśli jej brak (lub jest niepoprawna) użyj domyślnej wartości
+pub fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+// Ile dni po usunięciu konta można je jeszcze przywrócić
+pub fn delete_grace_period() -> Duration {
+    Duration::days(env_or("DELETE_GRACE_DAYS", 30))
+}
+
+// Katalog na wygenerowane archiwa z danymi użytkowników
+pub fn export_dir() -> PathBuf {
+    PathBuf::from(env_or("EXPORT_DIR", String::from("exports")))
+}
+
+// Jak długo działa link do pobrania eksportu
+pub fn export_link_ttl() -> Duration {
+    Duration::hours(env_or("EXPORT_LINK_TTL_HOURS", 24))
+}
+
+// Do tylu postów eksport generujemy od razu, powyżej — w tle
+pub fn export_sync_max_posts() -> u64 {
+    env_or("EXPORT_SYNC_MAX_POSTS", 500)
+}
+
+// Adresy reverse proxy (po przecinku), którym wierzymy w nagłówku X-Forwarded-For;
+// bez nich do logu audytu trafia adres połączenia
+pub fn trusted_proxies() -> Vec<IpAddr> {
+    env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+// Backend wyszukiwania: "postgres" (pełnotekstowe w bazie) albo "tantivy" (indeks na dysku)
+pub fn search_backend() -> SearchBackend {
+    env_or("SEARCH_BACKEND", SearchBackend::Postgres)
+}
+
+// Katalog wbudowanego indeksu wyszukiwania
+pub fn search_index_dir() -> PathBuf {
+    PathBuf::from(env_or("SEARCH_INDEX_DIR", String::from("search-index")))
+}
+
+// Czy zapisy i usunięcia muszą podawać If-Match (bez niego 428); domyślnie nagłówek jest opcjonalny
+pub fn require_if_match() -> bool {
+    env_or("REQUIRE_IF_MATCH", false)
+}
+
+// Jak długo pamiętamy odpowiedź dla klucza Idempotency-Key
+pub fn idempotency_ttl() -> Duration {
+    Duration::hours(env_or("IDEMPOTENCY_TTL_HOURS", 24))
+}
+
+// Największa treść żądania z Idempotency-Key — middleware trzyma ją w pamięci, zanim
+// zobaczy ją handler i jego własne limity
+pub fn idempotency_max_body_bytes() -> usize {
+    env_or("IDEMPOTENCY_MAX_BODY_BYTES", 1024 * 1024)
+}
+
+// Gdzie trzymamy załączniki: "local" (katalog na dysku) albo "s3" (S3 lub zgodny serwer, np. MinIO)
+pub fn storage_backend() -> StorageBackend {
+    env_or("STORAGE_BACKEND", StorageBackend::Local)
+}
+
+// Katalog załączników dla backendu lokalnego
+pub fn attachments_dir() -> PathBuf {
+    PathBuf::from(env_or("ATTACHMENTS_DIR", String::from("attachments")))
+}
+
+// Największy dopuszczalny plik (w bajtach), domyślnie 10 MiB
+pub fn attachment_max_bytes() -> usize {
+    env_or("ATTACHMENT_MAX_BYTES", 10 * 1024 * 1024)
+}
+
+// Jak długo działa podpisany link do pobrania załącznika
+pub fn attachment_url_ttl() -> Duration {
+    Duration::minutes(env_or("ATTACHMENT_URL_TTL_MINUTES", 15))
+}
+
+// Ustawienia backendu S3; adres (`S3_ENDPOINT`) pozwala wskazać lokalny serwer zgodny z S3
+pub fn s3_config() -> Option<S3Config> {
+    Some(S3Config {
+        endpoint: env::var("S3_ENDPOINT").ok()?,
+        bucket: env::var("S3_BUCKET").ok()?,
+        region: env_or("S3_REGION", String::from("us-east-1")),
+        access_key: env::var("S3_ACCESS_KEY").ok()?,
+        secret_key: env::var("S3_SECRET_KEY").ok()?,
+    })
+}
+
+// Limity roli: `QUOTA_USER_*` i `QUOTA_ADMIN_*` (MAX_POSTS, MAX_ATTACHMENT_BYTES,
+// MAX_REQUESTS_PER_DAY); wartość ujemna oznacza brak limitu, admini domyślnie go nie mają
+pub fn role_quota(role: &Role) -> Limits {
+    let (prefix, posts, bytes, requests) = match role {
+        Role::User => ("QUOTA_USER", 1000, 100 * 1024 * 1024, 5000),
+        Role::Admin => ("QUOTA_ADMIN", -1, -1, -1),
+    };
+    Limits {
+        max_posts: limit(env_or(&format!("{}_MAX_POSTS", prefix), posts)),
+        max_attachment_bytes: limit(env_or(&format!("{}_MAX_ATTACHMENT_BYTES", prefix), bytes)),
+        max_requests_per_day: limit(env_or(
+            &format!("{}_MAX_REQUESTS_PER_DAY", prefix),
+            requests,
+        )),
+    }
+}
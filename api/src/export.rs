@@ -0,0 +1,302 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use actix_web::web;
+use chrono::Utc;
+use sea_orm::entity::prelude::*;
+use sea_orm::{ActiveValue::Set, Condition, DatabaseConnection};
+use serde::Serialize;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::audit::{self, EventType};
+use crate::config::{export_dir, export_link_ttl};
+use crate::jwt::random_token;
+use crate::{post, user};
+
+type ExportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "ready")]
+    Ready,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "data_exports")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub status: ExportStatus,
+    #[serde(skip_serializing)]
+    pub file_path: Option<String>,
+    #[serde(skip_serializing)]
+    pub download_token: Option<String>,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at < Utc::now())
+    }
+
+    // Odpowiedź dla endpointu statusu — link tylko dla gotowego, ważnego eksportu
+    pub fn status_json(&self) -> serde_json::Value {
+        let download_url = match (&self.status, &self.download_token) {
+            (ExportStatus::Ready, Some(token)) if !self.is_expired() => {
+                Some(format!("/export/{}", token))
+            }
+            _ => None,
+        };
+
+        serde_json::json!({
+            "id": self.id,
+            "status": self.status,
+            "created_at": self.created_at,
+            "expires_at": self.expires_at,
+            "download_url": download_url
+        })
+    }
+}
+
+// Profil bez hasła — nigdy nie eksportujemy hasha
+#[derive(Serialize)]
+struct ProfileExport {
+    id: i32,
+    name: String,
+    lastname: String,
+    age: i32,
+    email: String,
+    created_at: DateTimeWithTimeZone,
+    updated_at: DateTimeWithTimeZone,
+}
+
+impl From<user::Model> for ProfileExport {
+    fn from(u: user::Model) -> Self {
+        ProfileExport {
+            id: u.id,
+            name: u.name,
+            lastname: u.lastname,
+            age: u.age,
+            email: u.email,
+            created_at: u.created_at,
+            updated_at: u.updated_at,
+        }
+    }
+}
+
+// Sesje nie są przechowywane (JWT jest bezstanowy) — odtwarzamy je z udanych logowań
+#[derive(Serialize)]
+struct SessionExport {
+    logged_in_at: DateTimeWithTimeZone,
+    ip: Option<String>,
+    user_agent: Option<String>,
+}
+
+// Płaski wiersz do CSV — pole `changes` jako tekst JSON
+#[derive(Serialize)]
+struct AuditEventRow {
+    id: i32,
+    event_type: EventType,
+    actor_id: Option<i32>,
+    target_type: String,
+    target_id: Option<i32>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    changes: Option<String>,
+    created_at: DateTimeWithTimeZone,
+}
+
+impl From<&audit::Model> for AuditEventRow {
+    fn from(e: &audit::Model) -> Self {
+        AuditEventRow {
+            id: e.id,
+            event_type: e.event_type.clone(),
+            actor_id: e.actor_id,
+            target_type: e.target_type.clone(),
+            target_id: e.target_id,
+            ip: e.ip.clone(),
+            user_agent: e.user_agent.clone(),
+            changes: e.changes.as_ref().map(|c| c.to_string()),
+            created_at: e.created_at,
+        }
+    }
+}
+
+// Wszystko, co trafia do archiwum
+struct ExportData {
+    profile: ProfileExport,
+    posts: Vec<post::Model>,
+    sessions: Vec<SessionExport>,
+    audit_events: Vec<audit::Model>,
+}
+
+// Wygeneruj archiwum dla zlecenia i oznacz je jako gotowe (albo nieudane)
+pub async fn generate(db: &DatabaseConnection, job: Model) {
+    let (job_id, user_id) = (job.id, job.user_id);
+    let mut active: ActiveModel = job.into();
+
+    match build_archive(db, job_id, user_id).await {
+        Ok(path) => {
+            active.status = Set(ExportStatus::Ready);
+            active.file_path = Set(Some(path.to_string_lossy().into_owned()));
+            active.download_token = Set(Some(random_token(48)));
+            active.expires_at = Set(Some((Utc::now() + export_link_ttl()).into()));
+        }
+        Err(e) => {
+            eprintln!("Export {} failed: {}", job_id, e);
+            active.status = Set(ExportStatus::Failed);
+        }
+    }
+
+    if let Err(e) = active.update(db).await {
+        eprintln!("Failed to update export {}: {}", job_id, e);
+    }
+}
+
+async fn build_archive(
+    db: &DatabaseConnection,
+    job_id: i32,
+    user_id: i32,
+) -> ExportResult<PathBuf> {
+    let user = user::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .ok_or("user not found")?;
+
+    let posts = post::find_active()
+        .filter(post::Column::UserId.eq(user_id))
+        .all(db)
+        .await?;
+
+    // Zdarzenia wykonane przez użytkownika albo dotyczące jego konta
+    let audit_events = audit::Entity::find()
+        .filter(
+            Condition::any()
+                .add(audit::Column::ActorId.eq(user_id))
+                .add(
+                    Condition::all()
+                        .add(audit::Column::TargetType.eq("user"))
+                        .add(audit::Column::TargetId.eq(user_id)),
+                ),
+        )
+        .all(db)
+        .await?;
+
+    let sessions = audit_events
+        .iter()
+        .filter(|e| e.event_type == EventType::UserLogin)
+        .map(|e| SessionExport {
+            logged_in_at: e.created_at,
+            ip: e.ip.clone(),
+            user_agent: e.user_agent.clone(),
+        })
+        .collect();
+
+    let data = ExportData {
+        profile: user.into(),
+        posts,
+        sessions,
+        audit_events,
+    };
+
+    let dir = export_dir();
+    let path = dir.join(format!("export-{}-{}.zip", user_id, job_id));
+
+    // Zapis pliku jest blokujący — przenosimy go poza wątek obsługujący żądania
+    let target = path.clone();
+    web::block(move || {
+        fs::create_dir_all(&dir)?;
+        write_zip(&target, &data)
+    })
+    .await??;
+
+    Ok(path)
+}
+
+fn write_zip(path: &Path, data: &ExportData) -> ExportResult<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default();
+
+    let json = serde_json::json!({
+        "generated_at": Utc::now(),
+        "profile": data.profile,
+        "posts": data.posts,
+        "sessions": data.sessions,
+        "audit_events": data.audit_events,
+    });
+    zip.start_file("data.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&json)?)?;
+
+    zip.start_file("profile.csv", options)?;
+    zip.write_all(&to_csv(std::slice::from_ref(&data.profile))?)?;
+
+    zip.start_file("posts.csv", options)?;
+    zip.write_all(&to_csv(&data.posts)?)?;
+
+    zip.start_file("sessions.csv", options)?;
+    zip.write_all(&to_csv(&data.sessions)?)?;
+
+    let audit_rows: Vec<AuditEventRow> = data.audit_events.iter().map(Into::into).collect();
+    zip.start_file("audit_events.csv", options)?;
+    zip.write_all(&to_csv(&audit_rows)?)?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn to_csv<T: Serialize>(rows: &[T]) -> ExportResult<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+// Usuń wygasłe eksporty razem z plikami
+pub async fn purge_expired(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let expired = Entity::find()
+        .filter(Column::ExpiresAt.lt(Utc::now()))
+        .all(db)
+        .await?;
+
+    for job in &expired {
+        if let Some(path) = &job.file_path {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    let ids: Vec<i32> = expired.iter().map(|job| job.id).collect();
+    let res = Entity::delete_many()
+        .filter(Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
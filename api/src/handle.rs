@@ -1,22 +1,53 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use crate::attachment::{self, AttachmentResponse, DownloadQuery, VariantsStatus};
+use crate::attachment_variant;
+use crate::audit::{self, AuditQuery, EventType, NewEvent};
+use crate::comment::{self, CommentCreate, CommentUpdate};
+use crate::config::{attachment_max_bytes, delete_grace_period, export_sync_max_posts};
+use crate::export::{self, ExportStatus};
 use crate::jwt::hash_password;
-use crate::jwt::Claims;
-use crate::jwt::{generate_jwt, vaildate_hash};
+use crate::jwt::{auth_cookies, claims_from_request, expired_auth_cookies, generate_csrf_token};
+use crate::jwt::{generate_jwt, random_token, request_token, vaildate_hash, TokenSource};
+use crate::org_membership::{self, MemberAdd, OrgMember, OrgRole};
+use crate::organization::{self, OrgCreate, OrgSummary, SetOrgPublic, Tenant};
 use crate::post::ActiveModel as ActiveModel_todo;
 use crate::post::Column as PostColumn;
 use crate::post::Entity as Entity_post;
-use crate::post::PostCreate;
-use crate::user::{self, UserCreate};
+use crate::post::{
+    self, ContentQuery, DueSoonQuery, MovePost, OccurrencesQuery, PostCreate, PostListQuery,
+    PostResponse, PostUpdate, Priority, SchedulePost, SetVisibility, TagMatch, TodoStatus,
+    Visibility,
+};
+use crate::post_revision::{self, DiffQuery};
+use crate::post_share::{self, Access, ShareCreate, SharedPost};
+use crate::post_tag;
+use crate::quota::{self, Meter, QuotaUpdate, Usage};
+use crate::reaction::{self, ReactedQuery, ReactionKind};
+use crate::rrule::RRule;
+use crate::search::{self, SearchQuery};
+use crate::search_index;
+use crate::storage::Storage;
+use crate::tag::{self, TagQuery};
+use crate::thumbnail::{self, Cleaned};
+use crate::user::{self, LoginRequest, Role, UserCreate};
 use crate::user::{ActiveModel, Entity};
-use actix_web::{web, HttpRequest, HttpResponse, Responder};
-use jsonwebtoken::{decode, DecodingKey, Validation};
+use crate::version::{self, etag, precondition_failed, update_versioned};
+use actix_multipart::Multipart;
+use actix_web::http::header::ContentDisposition;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{http::header, rt, web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use futures_util::StreamExt;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::ColumnTrait;
 use sea_orm::DbConn;
-use sea_orm::DbErr;
 use sea_orm::QueryFilter;
 use sea_orm::{ActiveModelTrait, EntityTrait, Set}; // Dodaj ten import, aby móc używać eq
-use serde::Serialize;
+use sea_orm::{Condition, ConnectionTrait, DbErr, SqlErr};
+use sea_orm::{ModelTrait, PaginatorTrait, QueryOrder, QuerySelect, TransactionTrait};
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize)]
 pub struct UserWithPosts {
@@ -24,21 +55,42 @@ pub struct UserWithPosts {
     pub name: String,
     pub lastname: String,
     pub age: i32,
-    pub email: String,
-    pub posts: Option<Vec<PostCreate>>,
+    // Adres e-mail widzi tylko właściciel konta i administrator
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    pub posts: Option<Vec<PostResponse>>,
 }
-pub async fn register(db: web::Data<DbConn>, user: web::Json<UserCreate>) -> impl Responder {
+
+// Publiczny profil: bez danych kontaktowych i tylko z publicznymi postami
+#[derive(Serialize)]
+pub struct PublicProfile {
+    pub id: i32,
+    pub name: String,
+    pub lastname: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub posts: Vec<PostResponse>,
+}
+
+// `/login?mode=cookie` — token trafia do ciasteczka HttpOnly zamiast do odpowiedzi
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    pub mode: Option<String>,
+}
+pub async fn register(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    user: web::Json<UserCreate>,
+) -> impl Responder {
     // Sprawdzamy, czy użytkownik z takim emailem już istnieje
     let existing_user = Entity::find()
         .filter(user::Column::Email.eq(&user.email)) // Poprawione użycie Column::Email
         .one(&**db)
         .await;
 
-    match existing_user {
-        Ok(Some(_)) => {
-            return HttpResponse::BadRequest().body("User already exists");
-        }
-        _ => {}
+    if let Ok(Some(_)) = existing_user {
+        return HttpResponse::BadRequest().body("User already exists");
     }
 
     // Haszowanie hasła przed zapisaniem
@@ -55,11 +107,33 @@ pub async fn register(db: web::Data<DbConn>, user: web::Json<UserCreate>) -> imp
     };
 
     // Zapisujemy użytkownika w bazie danych
-    let inserted_user = Entity::insert(new_user).exec(&**db).await.unwrap();
+    // (ActiveModel::insert, żeby before_save ustawił created_at/updated_at)
+    let inserted_user = new_user.insert(&**db).await.unwrap();
 
     // Uzyskanie ID wstawionego użytkownika
-    // Inna opcja, zamiast korzystać z deprecated `last_insert_id`, można dostać ID z inserted_user:
-    let _user_id = inserted_user.last_insert_id; // Użyj `unwrap_or_default` w razie braku ID
+    let user_id = inserted_user.id;
+
+    // Każdy zaczyna we własnej organizacji, do której może potem zapraszać innych
+    let personal = organization::personal_name(&inserted_user.name);
+    if organization::create_with_owner(&db, &personal, false, user_id)
+        .await
+        .is_err()
+    {
+        return HttpResponse::InternalServerError().body("Failed to create workspace");
+    }
+
+    audit::record(
+        &db,
+        &req,
+        NewEvent {
+            event_type: EventType::UserRegister,
+            actor_id: Some(user_id),
+            target_type: "user",
+            target_id: Some(user_id),
+            changes: audit::diff(None::<&user::Model>, &inserted_user),
+        },
+    )
+    .await;
 
     // Możesz zwrócić użytkownikowi token po zapisaniu
     //let token = generate_jwt(&user_id.to_string());
@@ -69,9 +143,14 @@ pub async fn register(db: web::Data<DbConn>, user: web::Json<UserCreate>) -> imp
     }))
 }
 
-pub async fn login(db: web::Data<DbConn>, info: web::Json<UserCreate>) -> impl Responder {
-    // Szukamy użytkownika po emailu
-    let user = Entity::find()
+pub async fn login(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<LoginQuery>,
+    info: web::Json<UserCreate>,
+) -> impl Responder {
+    // Szukamy użytkownika po emailu (konta usunięte nie mogą się logować)
+    let user = user::find_active()
         .filter(user::Column::Email.eq(&info.email)) // Poprawione użycie Column::Email
         .one(&**db)
         .await
@@ -81,118 +160,351 @@ pub async fn login(db: web::Data<DbConn>, info: web::Json<UserCreate>) -> impl R
         Some(user) => {
             // Sprawdzamy, czy hasło się zgadza
             if vaildate_hash(&info.password, &user.password) {
-                let token = generate_jwt(&user.id.to_string());
+                // Użytkownik usunięty ze wszystkich organizacji dostaje nową, własną
+                let org_id = match organization::default_for(&db, user.id).await {
+                    Ok(Some(org_id)) => org_id,
+                    Ok(None) => {
+                        let personal = organization::personal_name(&user.name);
+                        match organization::create_with_owner(&db, &personal, false, user.id).await
+                        {
+                            Ok(org) => org.id,
+                            Err(_) => {
+                                return HttpResponse::InternalServerError()
+                                    .body("Failed to create workspace")
+                            }
+                        }
+                    }
+                    Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+                };
+                let token = generate_jwt(&user.id.to_string(), org_id);
+
+                audit::record(
+                    &db,
+                    &req,
+                    NewEvent {
+                        event_type: EventType::UserLogin,
+                        actor_id: Some(user.id),
+                        target_type: "user",
+                        target_id: Some(user.id),
+                        changes: None,
+                    },
+                )
+                .await;
+
+                if query.mode.as_deref() == Some("cookie") {
+                    let csrf_token = generate_csrf_token();
+                    let (auth_cookie, csrf_cookie) = auth_cookies(&token, &csrf_token);
+                    return HttpResponse::Ok()
+                        .cookie(auth_cookie)
+                        .cookie(csrf_cookie)
+                        .json(serde_json::json!({
+                            "csrf_token": csrf_token,
+                            "user_id": user.id,
+                            "org_id": org_id
+                        }));
+                }
+
                 HttpResponse::Ok().json(serde_json::json!({
                     "token": token,
-                    "user_id": user.id
+                    "user_id": user.id,
+                    "org_id": org_id
                 }))
             } else {
+                audit::record(
+                    &db,
+                    &req,
+                    NewEvent {
+                        event_type: EventType::UserLoginFailed,
+                        actor_id: None,
+                        target_type: "user",
+                        target_id: Some(user.id),
+                        changes: None,
+                    },
+                )
+                .await;
                 HttpResponse::Unauthorized().body("Invalid credentials")
             }
         }
-        None => HttpResponse::NotFound().body("User not found"),
+        None => {
+            audit::record(
+                &db,
+                &req,
+                NewEvent {
+                    event_type: EventType::UserLoginFailed,
+                    actor_id: None,
+                    target_type: "user",
+                    target_id: None,
+                    changes: None,
+                },
+            )
+            .await;
+            HttpResponse::NotFound().body("User not found")
+        }
     }
 }
 
+pub async fn logout() -> impl Responder {
+    let (auth_cookie, csrf_cookie) = expired_auth_cookies();
+    HttpResponse::Ok()
+        .cookie(auth_cookie)
+        .cookie(csrf_cookie)
+        .json(serde_json::json!({
+            "message": "Logged out"
+        }))
+}
+
 pub async fn update(
     db: web::Data<DbConn>,
     req: HttpRequest,
     user: web::Json<UserCreate>,
 ) -> impl Responder {
-    // Pobierz Authorization header
-    let auth_header = match req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-    {
-        Some(h) => h,
-        None => return HttpResponse::Unauthorized().body("Missing Authorization header"),
-    };
-
-    // Wyciągnij token
-    let token = match auth_header.strip_prefix("Bearer ") {
-        Some(t) => t,
-        None => return HttpResponse::Unauthorized().body("Invalid token format"),
-    };
-
-    // Dekoduj token
-    let token_data = match decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(std::env::var("JWT_SECRET").unwrap().as_bytes()),
-        &Validation::default(),
-    ) {
-        Ok(data) => data,
-        Err(_) => return HttpResponse::Unauthorized().body("Invalid token"),
+    // Dekoduj token (nagłówek Bearer albo ciasteczko)
+    let claims = match claims_from_request(&req) {
+        Some(claims) => claims,
+        None => return HttpResponse::Unauthorized().body("Invalid token"),
     };
 
     // Parsuj user_id z tokena
-    let user_id = token_data.claims.sub.parse::<i32>().unwrap_or(0);
+    let user_id = claims.sub.parse::<i32>().unwrap_or(0);
 
     // Znajdź użytkownika
-    let existing = match Entity::find_by_id(user_id).one(&**db).await {
+    let existing = match user::find_active_by_id(user_id).one(&**db).await {
         Ok(Some(u)) => u,
         Ok(None) => return HttpResponse::NotFound().body("User not found"),
         Err(_) => return HttpResponse::InternalServerError().body("Database error"),
     };
+    if let Some(response) = precondition_failed(&req, existing.version) {
+        return response;
+    }
 
-    // Zrób hash nowego hasła
+    // Zrób hash nowego hasła (do logu trafia tylko informacja, że się zmieniło)
+    let password_changed = !vaildate_hash(&user.password, &existing.password);
     let hashed_password = hash_password(&user.password);
 
     // Aktualizuj dane
+    let before = existing.clone();
     let mut updated_user: ActiveModel = existing.into();
     updated_user.name = Set(user.name.clone());
     updated_user.lastname = Set(user.lastname.clone());
     updated_user.age = Set(user.age);
     updated_user.email = Set(user.email.clone());
     updated_user.password = Set(hashed_password);
+    updated_user.updated_by = Set(Some(user_id));
+
+    // Zapisz zmiany (tylko jeśli nikt nie zmienił konta od odczytu)
+    let after = match update_versioned(updated_user, &**db, user::Column::Version).await {
+        Ok(after) => after,
+        Err(e) if version::is_conflict(&e) => {
+            return HttpResponse::PreconditionFailed().body("Resource has been modified")
+        }
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to update user"),
+    };
 
-    // Zapisz zmiany
-    if let Err(_) = updated_user.update(&**db).await {
-        return HttpResponse::InternalServerError().body("Failed to update user");
+    let mut changes = audit::diff(Some(&before), &after);
+    if password_changed {
+        let changes = changes.get_or_insert_with(|| serde_json::json!({}));
+        changes["password"] = serde_json::json!("changed");
     }
+    audit::record(
+        &db,
+        &req,
+        NewEvent {
+            event_type: EventType::UserUpdate,
+            actor_id: Some(user_id),
+            target_type: "user",
+            target_id: Some(user_id),
+            changes,
+        },
+    )
+    .await;
 
-    HttpResponse::Ok().json(serde_json::json!({
-        "message": "User updated successfully"
-    }))
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag(after.version)))
+        .json(serde_json::json!({
+            "message": "User updated successfully"
+        }))
 }
 
 pub async fn delete(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
-    // Pobierz Authorization header
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok());
-
-    if let Some(auth_header) = auth_header {
-        if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            // Dekoduj token
-            let token_data = decode::<Claims>(
-                token,
-                &DecodingKey::from_secret(std::env::var("JWT_SECRET").unwrap().as_bytes()),
-                &Validation::default(),
-            );
-
-            if let Ok(token_data) = token_data {
-                let user_id = token_data.claims.sub.parse::<i32>().unwrap_or(0);
-
-                // Sprawdź, czy użytkownik istnieje
-                match Entity::find_by_id(user_id).one(&**db).await {
-                    Ok(Some(user)) => {
-                        // Usuń użytkownika
-                        let _ = Entity::delete_by_id(user_id).exec(&**db).await;
-                        return HttpResponse::Ok().json(serde_json::json!({
-                            "message": "User deleted successfully",
-                            "deleted_user": user
-                        }));
-                    }
-                    Ok(None) => return HttpResponse::NotFound().body("User not found"),
-                    Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    // Dekoduj token (nagłówek Bearer albo ciasteczko)
+    let claims = match claims_from_request(&req) {
+        Some(claims) => claims,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let user_id = claims.sub.parse::<i32>().unwrap_or(0);
+
+    // Sprawdź, czy użytkownik istnieje
+    match user::find_active_by_id(user_id).one(&**db).await {
+        Ok(Some(user)) => {
+            if let Some(response) = precondition_failed(&req, user.version) {
+                return response;
+            }
+
+            // Miękkie usunięcie — konto i posty można przywrócić w okresie karencji,
+            // po nim zadanie w tle usuwa je na stałe
+            let deleted_at: DateTimeWithTimeZone = Utc::now().into();
+            match soft_delete_user(&db, user_id, user.version, deleted_at).await {
+                Ok(true) => search_index::remove_user_posts(vec![user_id]).await,
+                Ok(false) => {
+                    return HttpResponse::PreconditionFailed().body("Resource has been modified")
                 }
+                Err(_) => return HttpResponse::InternalServerError().body("Failed to delete user"),
             }
+            audit::record(
+                &db,
+                &req,
+                NewEvent {
+                    event_type: EventType::UserDelete,
+                    actor_id: Some(user_id),
+                    target_type: "user",
+                    target_id: Some(user_id),
+                    changes: None,
+                },
+            )
+            .await;
+            HttpResponse::Ok().json(serde_json::json!({
+                "message": "User deleted successfully",
+                "deleted_user": user,
+                "restore_until": deleted_at + delete_grace_period()
+            }))
         }
+        Ok(None) => HttpResponse::NotFound().body("User not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Oznacz użytkownika i jego posty tym samym znacznikiem czasu,
+// żeby przy przywracaniu wróciły dokładnie posty usunięte razem z kontem.
+// `false` — konto zmieniło się od odczytu w wersji `version` i nic nie usunęliśmy.
+async fn soft_delete_user(
+    db: &DbConn,
+    user_id: i32,
+    version: i32,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<bool, DbErr> {
+    let txn = db.begin().await?;
+
+    let res = Entity::update_many()
+        .col_expr(user::Column::DeletedAt, Expr::value(deleted_at))
+        .col_expr(
+            user::Column::Version,
+            Expr::col(user::Column::Version).add(1),
+        )
+        .filter(user::Column::Id.eq(user_id))
+        .filter(user::Column::Version.eq(version))
+        .exec(&txn)
+        .await?;
+    if res.rows_affected == 0 {
+        txn.rollback().await?;
+        return Ok(false);
+    }
+
+    Entity_post::update_many()
+        .col_expr(PostColumn::DeletedAt, Expr::value(deleted_at))
+        .col_expr(PostColumn::Version, Expr::col(PostColumn::Version).add(1))
+        .filter(PostColumn::UserId.eq(user_id))
+        .filter(PostColumn::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(true)
+}
+
+pub async fn restore(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    info: web::Json<LoginRequest>,
+) -> impl Responder {
+    // Szukamy usuniętego konta po emailu
+    let user = match Entity::find()
+        .filter(user::Column::Email.eq(&info.email))
+        .filter(user::Column::DeletedAt.is_not_null())
+        .one(&**db)
+        .await
+    {
+        Ok(Some(u)) => u,
+        Ok(None) => return HttpResponse::NotFound().body("No deleted account for this email"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    if !vaildate_hash(&info.password, &user.password) {
+        return HttpResponse::Unauthorized().body("Invalid credentials");
+    }
+
+    let deleted_at = match user.deleted_at {
+        Some(d) => d,
+        None => return HttpResponse::NotFound().body("No deleted account for this email"),
+    };
+    if deleted_at + delete_grace_period() < Utc::now() {
+        return HttpResponse::Gone().body("Restore window has expired");
+    }
+
+    match restore_user(&db, user.id, deleted_at).await {
+        Ok(restored) => search_index::sync_post_ids(&db, restored).await,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to restore user"),
     }
+    audit::record(
+        &db,
+        &req,
+        NewEvent {
+            event_type: EventType::UserRestore,
+            actor_id: Some(user.id),
+            target_type: "user",
+            target_id: Some(user.id),
+            changes: None,
+        },
+    )
+    .await;
 
-    HttpResponse::Unauthorized().body("Invalid or missing token")
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "User restored successfully",
+        "user_id": user.id
+    }))
+}
+
+// Zwraca id przywróconych postów
+async fn restore_user(
+    db: &DbConn,
+    user_id: i32,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<Vec<i32>, DbErr> {
+    let txn = db.begin().await?;
+
+    Entity::update_many()
+        .col_expr(
+            user::Column::DeletedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .col_expr(
+            user::Column::Version,
+            Expr::col(user::Column::Version).add(1),
+        )
+        .filter(user::Column::Id.eq(user_id))
+        .exec(&txn)
+        .await?;
+
+    let restored: Vec<i32> = Entity_post::find()
+        .select_only()
+        .column(PostColumn::Id)
+        .filter(PostColumn::UserId.eq(user_id))
+        .filter(PostColumn::DeletedAt.eq(deleted_at))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    Entity_post::update_many()
+        .col_expr(
+            PostColumn::DeletedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .col_expr(PostColumn::Version, Expr::col(PostColumn::Version).add(1))
+        .filter(PostColumn::UserId.eq(user_id))
+        .filter(PostColumn::DeletedAt.eq(deleted_at))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(restored)
 }
 
 pub async fn add_post(
@@ -200,173 +512,3276 @@ pub async fn add_post(
     req: HttpRequest,
     post: web::Json<PostCreate>,
 ) -> impl Responder {
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok());
-
-    if let Some(auth_header) = auth_header {
-        if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            // Dekoduj token JWT
-            let jwt_secret = std::env::var("JWT_SECRET").unwrap();
-            let token_data = decode::<Claims>(
-                token,
-                &DecodingKey::from_secret(jwt_secret.as_bytes()),
-                &Validation::default(),
-            );
-
-            if let Ok(token_data) = token_data {
-                let user_id = token_data.claims.sub.parse::<i32>().unwrap_or(0);
-
-                // Sprawdź, czy użytkownik istnieje
-                match Entity::find_by_id(user_id).one(&**db).await {
-                    Ok(Some(_user)) => {
-                        // Sprawdź czy post o takim tytule już istnieje
-                        let existing_post = Entity_post::find()
-                            .filter(PostColumn::Title.eq(&post.title))
-                            .one(&**db)
-                            .await;
-
-                        if let Ok(Some(_)) = existing_post {
-                            return HttpResponse::BadRequest().body("Post already exists");
-                        }
+    // Token JWT (nagłówek Bearer albo ciasteczko) wskazuje też organizację posta
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let user_id = tenant.user_id;
 
-                        // Tworzymy i zapisujemy nowy post
-                        let new_post = ActiveModel_todo {
-                            title: Set(post.title.clone()),
-                            content: Set(post.content.clone()),
-                            user_id: Set(user_id),
-                            ..Default::default()
-                        };
-
-                        match new_post.insert(&**db).await {
-                            Ok(saved_post) => HttpResponse::Created().json(saved_post),
-                            Err(e) => {
-                                println!("Post insert error: {:?}", e);
-                                HttpResponse::InternalServerError().body("Failed to save post")
-                            }
-                        }
+    // Todo cykliczne potrzebuje poprawnej reguły i terminu pierwszego wystąpienia
+    if let Some(rule) = &post.rrule {
+        if let Err(e) = rule.parse::<RRule>() {
+            return HttpResponse::BadRequest().body(e);
+        }
+        if post.due_at.is_none() {
+            return HttpResponse::BadRequest().body("Recurring todos need due_at");
+        }
+    }
+
+    // Termin publikacji w przyszłości oznacza szkic; szkic z minionym terminem
+    // opublikuje najbliższy przebieg zadania w tle
+    let draft = post.draft.unwrap_or(false) || post.publish_at.is_some_and(|at| at > Utc::now());
+
+    // Sprawdź, czy użytkownik istnieje
+    match user::find_active_by_id(user_id).one(&**db).await {
+        Ok(Some(user)) => {
+            // Podzadanie można dodać tylko pod własne todo
+            if let Some(parent_id) = post.parent_id
+                && find_own_post(&db, tenant, parent_id).await.is_err()
+            {
+                return HttpResponse::BadRequest().body("Parent todo not found");
+            }
+
+            let tags = match tag::normalize(post.tags.as_deref().unwrap_or_default()) {
+                Ok(tags) => tags,
+                Err(e) => return HttpResponse::BadRequest().body(e),
+            };
+
+            // Tworzymy i zapisujemy nowy post
+            let mut new_post = ActiveModel_todo {
+                title: Set(post.title.clone()),
+                content: Set(post.content.clone()),
+                content_format: Set(post.content_format.unwrap_or_default()),
+                user_id: Set(user_id),
+                org_id: Set(tenant.org_id),
+                created_by: Set(Some(user_id)),
+                updated_by: Set(Some(user_id)),
+                due_at: Set(post.due_at),
+                priority: Set(post.priority.unwrap_or(Priority::Normal)),
+                rrule: Set(post.rrule.clone()),
+                recurrence_start: Set(post.rrule.as_ref().and(post.due_at)),
+                parent_id: Set(post.parent_id),
+                visibility: Set(post.visibility.unwrap_or_default()),
+                draft: Set(draft),
+                publish_at: Set(post.publish_at.filter(|_| draft)),
+                ..Default::default()
+            };
+            new_post.set_status(post.status.unwrap_or(TodoStatus::Open));
+
+            match insert_post_with_tags(&db, &user, new_post, &tags).await {
+                Ok(saved_post) => {
+                    search_index::sync_posts(vec![saved_post.clone()]).await;
+                    audit::record(
+                        &db,
+                        &req,
+                        NewEvent {
+                            event_type: EventType::PostCreate,
+                            actor_id: Some(user_id),
+                            target_type: "post",
+                            target_id: Some(saved_post.id),
+                            changes: audit::diff(None::<&post::Model>, &saved_post),
+                        },
+                    )
+                    .await;
+                    match post_responses(&db, vec![saved_post]).await {
+                        Ok(mut posts) => HttpResponse::Created().json(posts.remove(0)),
+                        Err(_) => HttpResponse::InternalServerError().body("Database error"),
                     }
-                    Ok(None) => HttpResponse::NotFound().body("User not found"),
-                    Err(_) => HttpResponse::InternalServerError().body("Database error"),
                 }
-            } else {
-                HttpResponse::Unauthorized().body("Invalid token")
+                Err(QuotaWriteError::Exceeded(posts)) => HttpResponse::Forbidden().body(format!(
+                    "Post quota exceeded: the limit is {} posts",
+                    posts.limit.unwrap_or_default()
+                )),
+                // Unikalność tytułu (per użytkownik) pilnuje indeks idx_posts_user_title
+                Err(QuotaWriteError::Db(e)) if is_unique_violation(&e) => {
+                    HttpResponse::Conflict().body("You already have a post with this title")
+                }
+                Err(QuotaWriteError::Db(e)) => {
+                    eprintln!("Failed to insert post: {}", e);
+                    HttpResponse::InternalServerError().body("Failed to save post")
+                }
             }
-        } else {
-            HttpResponse::Unauthorized().body("Missing Bearer token")
         }
-    } else {
-        HttpResponse::Unauthorized().body("Authorization header missing")
+        Ok(None) => HttpResponse::NotFound().body("User not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
     }
 }
 
-pub async fn get_users_with_posts(db: web::Data<DbConn>) -> Result<Vec<UserWithPosts>, DbErr> {
-    let users = Entity::find()
-        .find_with_related(Entity_post)
-        .all(&**db)
-        .await?;
+// Post razem z tagami — błąd tagów nie zostawia zapisanego posta bez nich
+async fn insert_post_with_tags(
+    db: &DbConn,
+    owner: &user::Model,
+    new_post: ActiveModel_todo,
+    tags: &[String],
+) -> Result<post::Model, QuotaWriteError> {
+    let txn = db.begin().await?;
+    let usage = quota::locked_usage(&txn, owner).await?;
+    if usage.posts.exceeded_by(1) {
+        return Err(QuotaWriteError::Exceeded(usage.posts));
+    }
+    let saved = insert_post(&txn, new_post).await?;
+    set_post_tags(&txn, owner.id, saved.id, tags).await?;
+    txn.commit().await?;
+    Ok(saved)
+}
 
-    let result = users
+// Zapis, który nie zmieścił się w limicie konta (sprawdzanym w jego transakcji) albo padł w bazie
+enum QuotaWriteError {
+    Exceeded(Meter),
+    Db(DbErr),
+}
+
+impl From<DbErr> for QuotaWriteError {
+    fn from(e: DbErr) -> Self {
+        QuotaWriteError::Db(e)
+    }
+}
+
+// Pierwsze wystąpienie serii jest jej identyfikatorem (series_id = id)
+async fn insert_post<C: ConnectionTrait>(
+    db: &C,
+    new_post: ActiveModel_todo,
+) -> Result<post::Model, DbErr> {
+    let saved = new_post.insert(db).await?;
+    if saved.rrule.is_none() || saved.series_id.is_some() {
+        return Ok(saved);
+    }
+
+    let series_id = saved.id;
+    let mut series: ActiveModel_todo = saved.into();
+    series.series_id = Set(Some(series_id));
+    series.update(db).await
+}
+
+// Czy zapis odrzuciło ograniczenie unikalności w bazie
+fn is_unique_violation(e: &DbErr) -> bool {
+    matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
+}
+
+// Naruszenie unikalności konkretnego indeksu (Postgres podaje jego nazwę w komunikacie)
+fn violates_index(e: &DbErr, index: &str) -> bool {
+    matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(message))
+        if message.contains(&format!("\"{}\"", index)))
+}
+
+// user_id z tokena zalogowanego użytkownika
+fn current_user_id(req: &HttpRequest) -> Option<i32> {
+    claims_from_request(req).and_then(|claims| claims.sub.parse::<i32>().ok())
+}
+
+// Zalogowany użytkownik w organizacji z tokena — o ile nadal jest jej członkiem
+async fn current_tenant(db: &DbConn, req: &HttpRequest) -> Result<Tenant, HttpResponse> {
+    let claims = match claims_from_request(req) {
+        Some(claims) => claims,
+        None => return Err(HttpResponse::Unauthorized().body("Invalid or missing token")),
+    };
+    let user_id = match claims.sub.parse::<i32>() {
+        Ok(id) => id,
+        Err(_) => return Err(HttpResponse::Unauthorized().body("Invalid user ID in token")),
+    };
+
+    match org_membership::find_accepted()
+        .filter(org_membership::Column::OrgId.eq(claims.org))
+        .filter(org_membership::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(_)) => Ok(Tenant {
+            user_id,
+            org_id: claims.org,
+        }),
+        Ok(None) => {
+            Err(HttpResponse::Forbidden().body("You are not a member of this organization"))
+        }
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+// To samo na endpointach dostępnych także bez tokena; bez członkostwa — jak niezalogowany
+async fn viewer_tenant(db: &DbConn, req: &HttpRequest) -> Result<Option<Tenant>, DbErr> {
+    let claims = match claims_from_request(req) {
+        Some(claims) => claims,
+        None => return Ok(None),
+    };
+    let user_id = match claims.sub.parse::<i32>() {
+        Ok(id) => id,
+        Err(_) => return Ok(None),
+    };
+
+    Ok(org_membership::find_accepted()
+        .filter(org_membership::Column::OrgId.eq(claims.org))
+        .filter(org_membership::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .map(|_| Tenant {
+            user_id,
+            org_id: claims.org,
+        }))
+}
+
+// Nieusunięty post należący do zalogowanego użytkownika (w jego aktywnej organizacji)
+async fn find_own_post(
+    db: &DbConn,
+    tenant: Tenant,
+    post_id: i32,
+) -> Result<post::Model, HttpResponse> {
+    match post::find_active()
+        .filter(PostColumn::Id.eq(post_id))
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::UserId.eq(tenant.user_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(post)) => Ok(post),
+        Ok(None) => Err(HttpResponse::NotFound().body("Post not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+// Ile poziomów przodków sprawdzamy, szukając udostępnienia obejmującego podzadanie
+const MAX_SHARE_DEPTH: usize = 64;
+
+// Dostęp użytkownika do posta: właściciel albo przyjęte udostępnienie tego posta
+// lub któregoś z jego przodków (udostępnienie obejmuje całe poddrzewo)
+async fn post_access(
+    db: &DbConn,
+    user_id: i32,
+    post: &post::Model,
+) -> Result<Option<Access>, DbErr> {
+    if post.user_id == user_id {
+        return Ok(Some(Access::Owner));
+    }
+
+    let mut ids = vec![post.id];
+    let mut parent = post.parent_id;
+    while let Some(parent_id) = parent {
+        if ids.contains(&parent_id) || ids.len() > MAX_SHARE_DEPTH {
+            break;
+        }
+        ids.push(parent_id);
+        parent = Entity_post::find_by_id(parent_id)
+            .one(db)
+            .await?
+            .and_then(|p| p.parent_id);
+    }
+
+    let shares = post_share::find_accepted()
+        .filter(post_share::Column::UserId.eq(user_id))
+        .filter(post_share::Column::PostId.is_in(ids))
+        .all(db)
+        .await?;
+    Ok(shares
         .into_iter()
-        .map(|(u, posts)| UserWithPosts {
-            id: u.id,
-            name: u.name,
-            lastname: u.lastname,
-            age: u.age,
-            email: u.email,
-
-            // Correct the Post mapping issue
-            posts: Some(
-                posts
-                    .into_iter()
-                    .map(|p| PostCreate {
-                        title: p.title,
-                        content: p.content, // Fix the content mapping
-                    })
-                    .collect(),
-            ),
-        })
-        .collect();
+        .map(|share| Access::from(share.role))
+        .max())
+}
 
-    Ok(result)
+// Nieusunięty post z aktywnej organizacji, do którego zalogowany użytkownik ma
+// co najmniej `required` dostęp. Bez żadnego dostępu post wygląda jak nieistniejący.
+async fn find_post_with_access(
+    db: &DbConn,
+    tenant: Tenant,
+    post_id: i32,
+    required: Access,
+) -> Result<(post::Model, Access), HttpResponse> {
+    let post = match post::find_active()
+        .filter(PostColumn::Id.eq(post_id))
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return Err(HttpResponse::NotFound().body("Post not found")),
+        Err(_) => return Err(HttpResponse::InternalServerError().body("Database error")),
+    };
+
+    match post_access(db, tenant.user_id, &post).await {
+        Ok(Some(access)) if access >= required => Ok((post, access)),
+        Ok(Some(_)) => {
+            Err(HttpResponse::Forbidden().body("Insufficient permissions for this post"))
+        }
+        Ok(None) => Err(HttpResponse::NotFound().body("Post not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
 }
-// funkcja na określony limit czasu
-pub async fn get_users(db: web::Data<DbConn>) -> impl Responder {
-    // Corrected line where we pass db as web::Data<DbConn> directly
-    match get_users_with_posts(db).await {
-        Ok(users) => HttpResponse::Ok().json(users),
-        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+
+async fn set_todo_status(
+    db: &DbConn,
+    req: &HttpRequest,
+    post_id: i32,
+    status: TodoStatus,
+) -> HttpResponse {
+    let tenant = match current_tenant(db, req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let existing = match find_post_with_access(db, tenant, post_id, Access::Editor).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+    if let Some(response) = precondition_failed(req, existing.version) {
+        return response;
+    }
+
+    let was_done = existing.done;
+    let mut todo: ActiveModel_todo = existing.into();
+    todo.set_status(status);
+    todo.updated_by = Set(Some(tenant.user_id));
+
+    let (saved, next_occurrence) = match save_status(db, todo, status, was_done).await {
+        Ok(saved) => saved,
+        Err(e) if version::is_conflict(&e) => {
+            return HttpResponse::PreconditionFailed().body("Resource has been modified")
+        }
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to update post"),
+    };
+    // Status nie trafia do indeksu wyszukiwania, ale nowe wystąpienie serii już tak
+    if let Some(next) = &next_occurrence {
+        search_index::sync_posts(vec![next.clone()]).await;
     }
+
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, etag(saved.version)))
+        .json(serde_json::json!({
+            "todo": saved,
+            "next_occurrence": next_occurrence
+        }))
 }
 
-pub async fn settings(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
-    let auth_header = req
-        .headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok());
-
-    if let Some(auth_header) = auth_header {
-        if let Some(token) = auth_header.strip_prefix("Bearer ") {
-            // Decode token
-            if let Ok(token_data) = jsonwebtoken::decode::<Claims>(
-                token,
-                &jsonwebtoken::DecodingKey::from_secret(
-                    std::env::var("JWT_SECRET").unwrap().as_bytes(),
-                ),
-                &jsonwebtoken::Validation::default(),
-            ) {
-                // Parse user_id from `sub`
-                let user_id = match token_data.claims.sub.parse::<i32>() {
-                    Ok(id) => id,
-                    Err(_) => return HttpResponse::Unauthorized().body("Invalid user ID in token"),
-                };
+// Zmiana statusu razem z następnym wystąpieniem serii i jego tagami — wszystko albo nic
+async fn save_status(
+    db: &DbConn,
+    todo: ActiveModel_todo,
+    status: TodoStatus,
+    was_done: bool,
+) -> Result<(post::Model, Option<post::Model>), DbErr> {
+    let txn = db.begin().await?;
+    let saved = update_versioned(todo, &txn, PostColumn::Version).await?;
 
-                // Look for user
-                if let Ok(Some(_user)) = Entity::find_by_id(user_id).one(&**db).await {
-                    let user_with_post = Entity::find_by_id(user_id)
-                        .find_also_related(Entity_post)
-                        .all(&**db)
-                        .await;
-
-                    return match user_with_post {
-                        Ok(data) => {
-                            let mut user_map: HashMap<i32, UserWithPosts> = HashMap::new();
-
-                            for (user, maybe_post) in data {
-                                let entry =
-                                    user_map.entry(user.id).or_insert_with(|| UserWithPosts {
-                                        id: user.id,
-                                        name: user.name.clone(),
-                                        lastname: user.lastname.clone(),
-                                        age: user.age,
-                                        email: user.email.clone(),
-                                        posts: Some(vec![]),
-                                    });
-
-                                if let Some(post) = maybe_post {
-                                    // Przekształcenie post::Model -> PostCreate, jeśli potrzebne
-                                    let converted_post = PostCreate {
-                                        title: post.title.clone(),
-                                        content: post.content.clone(),
-                                        // inne pola, jeśli są
-                                    };
-
-                                    if let Some(ref mut posts) = entry.posts {
-                                        posts.push(converted_post);
-                                    }
-                                }
-                            }
+    // Ukończenie wystąpienia cyklicznego todo tworzy następne wystąpienie serii — chyba że
+    // już istnieje (ukończenie, ponowne otwarcie i ukończenie nie dubluje terminu)
+    let mut next_occurrence = None;
+    if status == TodoStatus::Done
+        && !was_done
+        && let Some(next) = saved.next_occurrence()
+    {
+        let series_id = saved.series_id.unwrap_or(saved.id);
+        let already_created = Entity_post::find()
+            .filter(
+                Condition::any()
+                    .add(PostColumn::SeriesId.eq(series_id))
+                    .add(PostColumn::Id.eq(series_id)),
+            )
+            .filter(PostColumn::Id.ne(saved.id))
+            .filter(PostColumn::DueAt.eq(next.due_at.clone().unwrap()))
+            .count(&txn)
+            .await?
+            > 0;
+        if !already_created {
+            let next = next.insert(&txn).await?;
+            copy_post_tags(&txn, saved.id, next.id).await?;
+            next_occurrence = Some(next);
+        }
+    }
 
-                            let result: Vec<UserWithPosts> = user_map.into_values().collect();
-                            HttpResponse::Ok().json(result)
-                        }
-                        Err(_) => HttpResponse::InternalServerError().body("Database error"),
-                    };
-                }
-            }
+    txn.commit().await?;
+    Ok((saved, next_occurrence))
+}
+
+pub async fn complete_todo(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    set_todo_status(&db, &req, path.into_inner(), TodoStatus::Done).await
+}
+
+pub async fn reopen_todo(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    set_todo_status(&db, &req, path.into_inner(), TodoStatus::Open).await
+}
+
+// Otwarte todo z terminem w podanym przedziale, najpilniejsze pierwsze
+async fn list_due(
+    db: &DbConn,
+    req: &HttpRequest,
+    from: Option<DateTimeWithTimeZone>,
+    to: DateTimeWithTimeZone,
+) -> HttpResponse {
+    let tenant = match current_tenant(db, req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    let mut select = post::find_active()
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::UserId.eq(tenant.user_id))
+        .filter(PostColumn::Done.eq(false))
+        .filter(PostColumn::DueAt.lt(to));
+    if let Some(from) = from {
+        select = select.filter(PostColumn::DueAt.gte(from));
+    }
+
+    match select
+        .order_by_asc(PostColumn::DueAt)
+        .order_by_desc(PostColumn::Priority)
+        .all(db)
+        .await
+    {
+        Ok(posts) => HttpResponse::Ok().json(posts),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn overdue_todos(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    list_due(&db, &req, None, Utc::now().into()).await
+}
+
+pub async fn due_soon_todos(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<DueSoonQuery>,
+) -> impl Responder {
+    let now = Utc::now();
+    let hours = query.hours.unwrap_or(24).clamp(1, 24 * 365);
+    list_due(
+        &db,
+        &req,
+        Some(now.into()),
+        (now + chrono::Duration::hours(hours)).into(),
+    )
+    .await
+}
+
+// Nieusunięte cykliczne todo dostępne dla zalogowanego użytkownika (razem z jego id)
+async fn find_recurring(
+    req: &HttpRequest,
+    db: &DbConn,
+    post_id: i32,
+    required: Access,
+) -> Result<(post::Model, i32), HttpResponse> {
+    let tenant = current_tenant(db, req).await?;
+    let (todo, _) = find_post_with_access(db, tenant, post_id, required).await?;
+    if todo.recurrence().is_none() {
+        return Err(HttpResponse::BadRequest().body("Todo is not recurring"));
+    }
+    Ok((todo, tenant.user_id))
+}
+
+pub async fn todo_occurrences(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<OccurrencesQuery>,
+) -> impl Responder {
+    let todo = match find_recurring(&req, &db, path.into_inner(), Access::Viewer).await {
+        Ok((todo, _)) => todo,
+        Err(response) => return response,
+    };
+
+    let count = query.count.unwrap_or(5).clamp(1, 100);
+    HttpResponse::Ok().json(serde_json::json!({
+        "series_id": todo.series_id,
+        "rrule": todo.rrule,
+        "occurrences": todo.upcoming_occurrences(count)
+    }))
+}
+
+// Pomiń bieżące wystąpienie — todo przechodzi na termin następnego
+pub async fn skip_occurrence(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let (todo, user_id) = match find_recurring(&req, &db, path.into_inner(), Access::Editor).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    if let Some(response) = precondition_failed(&req, todo.version) {
+        return response;
+    }
+    if todo.done {
+        return HttpResponse::Conflict().body("Occurrence is already completed");
+    }
+
+    let next_due = match todo.upcoming_occurrences(1).into_iter().next() {
+        Some(due) => due,
+        None => return HttpResponse::Conflict().body("Series has no further occurrences"),
+    };
+
+    let mut occurrence: ActiveModel_todo = todo.into();
+    occurrence.due_at = Set(Some(next_due));
+    occurrence.updated_by = Set(Some(user_id));
+
+    match update_versioned(occurrence, &**db, PostColumn::Version).await {
+        Ok(saved) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag(saved.version)))
+            .json(saved),
+        Err(e) if version::is_conflict(&e) => {
+            HttpResponse::PreconditionFailed().body("Resource has been modified")
         }
+        Err(_) => HttpResponse::InternalServerError().body("Failed to update post"),
+    }
+}
+
+// Zakończ serię: otwarte wystąpienia przestają się powtarzać
+pub async fn end_series(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let (todo, user_id) = match find_recurring(&req, &db, path.into_inner(), Access::Editor).await {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    let series_id = todo.series_id.unwrap_or(todo.id);
+
+    match Entity_post::update_many()
+        .col_expr(PostColumn::Rrule, Expr::value(Option::<String>::None))
+        .col_expr(PostColumn::UpdatedBy, Expr::value(user_id))
+        .col_expr(PostColumn::Version, Expr::col(PostColumn::Version).add(1))
+        .col_expr(
+            PostColumn::UpdatedAt,
+            Expr::value(DateTimeWithTimeZone::from(Utc::now())),
+        )
+        .filter(PostColumn::OrgId.eq(todo.org_id))
+        .filter(PostColumn::UserId.eq(todo.user_id))
+        .filter(PostColumn::SeriesId.eq(series_id))
+        .filter(PostColumn::Done.eq(false))
+        .filter(PostColumn::DeletedAt.is_null())
+        .exec(&**db)
+        .await
+    {
+        Ok(res) => HttpResponse::Ok().json(serde_json::json!({
+            "message": "Series ended",
+            "series_id": series_id,
+            "updated": res.rows_affected
+        })),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to end series"),
     }
+}
 
-    HttpResponse::Unauthorized().body("Invalid or missing token")
+// Wszystkie nieusunięte posty właściciela w organizacji — z nich budujemy drzewa podzadań
+async fn owner_posts(db: &DbConn, owner_id: i32, org_id: i32) -> Result<Vec<post::Model>, DbErr> {
+    post::find_active()
+        .filter(PostColumn::OrgId.eq(org_id))
+        .filter(PostColumn::UserId.eq(owner_id))
+        .order_by_asc(PostColumn::CreatedAt)
+        .all(db)
+        .await
+}
+
+pub async fn todo_tree(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let root = match find_post_with_access(&db, tenant, path.into_inner(), Access::Viewer).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    match owner_posts(&db, root.user_id, root.org_id).await {
+        Ok(posts) => HttpResponse::Ok().json(post::build_tree(root, posts)),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Przenieś todo (z poddrzewem) pod innego rodzica albo na najwyższy poziom
+pub async fn move_todo(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<MovePost>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_own_post(&db, tenant, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    if let Some(response) = precondition_failed(&req, todo.version) {
+        return response;
+    }
+
+    if let Some(parent_id) = body.parent_id {
+        let posts = match owner_posts(&db, todo.user_id, todo.org_id).await {
+            Ok(posts) => posts,
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        };
+        let parents: HashMap<i32, Option<i32>> =
+            posts.iter().map(|p| (p.id, p.parent_id)).collect();
+
+        if !parents.contains_key(&parent_id) {
+            return HttpResponse::BadRequest().body("Parent todo not found");
+        }
+
+        // Cykl: nowy rodzic jest samym todo albo leży w jego poddrzewie
+        let mut ancestor = Some(parent_id);
+        let mut steps = 0;
+        while let Some(current) = ancestor {
+            if current == todo.id || steps > parents.len() {
+                return HttpResponse::Conflict().body("Move would create a cycle");
+            }
+            ancestor = parents.get(&current).copied().flatten();
+            steps += 1;
+        }
+    }
+
+    let mut moved: ActiveModel_todo = todo.into();
+    moved.parent_id = Set(body.parent_id);
+    moved.updated_by = Set(Some(tenant.user_id));
+
+    match update_versioned(moved, &**db, PostColumn::Version).await {
+        Ok(saved) => HttpResponse::Ok()
+            .insert_header((header::ETAG, etag(saved.version)))
+            .json(saved),
+        Err(e) if version::is_conflict(&e) => {
+            HttpResponse::PreconditionFailed().body("Resource has been modified")
+        }
+        Err(_) => HttpResponse::InternalServerError().body("Failed to move todo"),
+    }
+}
+
+// Zapisz edycję posta: nowa wersja tytułu i treści trafia do historii rewizji przy zapisie.
+// If-Match sprawdza wywołujący; tu pilnujemy, żeby nikt nie zapisał posta w międzyczasie.
+async fn save_post_edit(
+    db: &DbConn,
+    req: &HttpRequest,
+    actor_id: i32,
+    before: post::Model,
+    mut edited: ActiveModel_todo,
+) -> HttpResponse {
+    edited.updated_by = Set(Some(actor_id));
+
+    let saved = match update_versioned(edited, db, PostColumn::Version).await {
+        Ok(saved) => saved,
+        Err(e) if version::is_conflict(&e) => {
+            return HttpResponse::PreconditionFailed().body("Resource has been modified")
+        }
+        // Unikalność tytułu (per właściciel) pilnuje indeks idx_posts_user_title
+        Err(e) if violates_index(&e, "idx_posts_user_title") => {
+            return HttpResponse::Conflict().body("A post with this title already exists")
+        }
+        // Numer rewizji zajęty przez równoległy zapis tego samego posta
+        Err(e) if violates_index(&e, "idx_post_revisions_post_revision") => {
+            return HttpResponse::Conflict().body("Post was modified concurrently, try again")
+        }
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to update post"),
+    };
+    search_index::sync_posts(vec![saved.clone()]).await;
+
+    audit::record(
+        db,
+        req,
+        NewEvent {
+            event_type: EventType::PostUpdate,
+            actor_id: Some(actor_id),
+            target_type: "post",
+            target_id: Some(saved.id),
+            changes: audit::diff(Some(&before), &saved),
+        },
+    )
+    .await;
+
+    let tag = etag(saved.version);
+    match post_responses(db, vec![saved]).await {
+        Ok(mut posts) => HttpResponse::Ok()
+            .insert_header((header::ETAG, tag))
+            .json(posts.remove(0)),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Przenieś własne todo razem z podzadaniami do kosza. Do końca okna przywracania można je
+// odzyskać, potem usunie je zadanie w tle.
+pub async fn delete_todo(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_own_post(&db, tenant, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    if let Some(response) = precondition_failed(&req, todo.version) {
+        return response;
+    }
+
+    let posts = match owner_posts(&db, todo.user_id, todo.org_id).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let ids = subtree_ids(todo.id, &posts);
+
+    let deleted_at: DateTimeWithTimeZone = Utc::now().into();
+    let deleted = match trash_posts(&db, &todo, &ids, deleted_at, tenant.user_id).await {
+        Ok(Some(deleted)) => deleted,
+        Ok(None) => return HttpResponse::PreconditionFailed().body("Resource has been modified"),
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to delete todo"),
+    };
+    search_index::sync_post_ids(&db, ids).await;
+
+    audit::record(
+        &db,
+        &req,
+        NewEvent {
+            event_type: EventType::PostDelete,
+            actor_id: Some(tenant.user_id),
+            target_type: "post",
+            target_id: Some(todo.id),
+            changes: None,
+        },
+    )
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Todo moved to trash",
+        "deleted": deleted,
+        "restore_until": deleted_at + delete_grace_period()
+    }))
+}
+
+// Id posta i wszystkich jego potomków wśród `posts`
+fn subtree_ids(root_id: i32, posts: &[post::Model]) -> Vec<i32> {
+    let mut ids = vec![root_id];
+    let mut i = 0;
+    while i < ids.len() {
+        let parent = ids[i];
+        ids.extend(
+            posts
+                .iter()
+                .filter(|p| p.parent_id == Some(parent) && !ids.contains(&p.id))
+                .map(|p| p.id)
+                .collect::<Vec<_>>(),
+        );
+        i += 1;
+    }
+    ids
+}
+
+// Wspólny `deleted_at` łączy posty usunięte razem — tak samo je potem przywracamy.
+// None, gdy ktoś zapisał post w międzyczasie.
+async fn trash_posts(
+    db: &DbConn,
+    todo: &post::Model,
+    ids: &[i32],
+    deleted_at: DateTimeWithTimeZone,
+    actor_id: i32,
+) -> Result<Option<u64>, DbErr> {
+    let txn = db.begin().await?;
+
+    let root = Entity_post::update_many()
+        .col_expr(PostColumn::DeletedAt, Expr::value(deleted_at))
+        .col_expr(PostColumn::UpdatedBy, Expr::value(actor_id))
+        .col_expr(PostColumn::Version, Expr::col(PostColumn::Version).add(1))
+        .filter(PostColumn::Id.eq(todo.id))
+        .filter(PostColumn::Version.eq(todo.version))
+        .filter(PostColumn::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+    if root.rows_affected == 0 {
+        txn.rollback().await?;
+        return Ok(None);
+    }
+
+    let children = Entity_post::update_many()
+        .col_expr(PostColumn::DeletedAt, Expr::value(deleted_at))
+        .col_expr(PostColumn::UpdatedBy, Expr::value(actor_id))
+        .col_expr(PostColumn::Version, Expr::col(PostColumn::Version).add(1))
+        .filter(PostColumn::Id.is_in(ids.iter().copied().filter(|id| *id != todo.id)))
+        .filter(PostColumn::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(Some(root.rows_affected + children.rows_affected))
+}
+
+// Własne todo w koszu bieżącej organizacji, które można jeszcze przywrócić
+pub async fn list_trash(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    let cutoff = Utc::now() - delete_grace_period();
+    match Entity_post::find()
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::UserId.eq(tenant.user_id))
+        .filter(PostColumn::DeletedAt.gt(cutoff))
+        .order_by_desc(PostColumn::DeletedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(posts) => HttpResponse::Ok().json(posts),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Przywróć todo z kosza razem z podzadaniami usuniętymi w tej samej operacji
+pub async fn restore_todo(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match Entity_post::find_by_id(path.into_inner())
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::UserId.eq(tenant.user_id))
+        .filter(PostColumn::DeletedAt.is_not_null())
+        .one(&**db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().body("No deleted todo with this id"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let Some(deleted_at) = todo.deleted_at else {
+        return HttpResponse::NotFound().body("No deleted todo with this id");
+    };
+    if deleted_at + delete_grace_period() < Utc::now() {
+        return HttpResponse::Gone().body("Restore window has expired");
+    }
+
+    // Podzadanie usunięte razem z rodzicem wraca tylko z nim
+    if let Some(parent_id) = todo.parent_id {
+        match post::find_active()
+            .filter(PostColumn::Id.eq(parent_id))
+            .one(&**db)
+            .await
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => return HttpResponse::Conflict().body("Restore the parent todo first"),
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        }
+    }
+
+    let user = match user::find_active_by_id(tenant.user_id).one(&**db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let restored = match untrash_posts(&db, &user, &todo, deleted_at).await {
+        Ok(ids) => {
+            let restored = ids.len();
+            search_index::sync_post_ids(&db, ids).await;
+            restored
+        }
+        Err(QuotaWriteError::Exceeded(posts)) => {
+            return HttpResponse::Forbidden().body(format!(
+                "Post quota exceeded: the limit is {} posts",
+                posts.limit.unwrap_or_default()
+            ))
+        }
+        Err(QuotaWriteError::Db(e)) if violates_index(&e, "idx_posts_user_title") => {
+            return HttpResponse::Conflict().body("You already have a post with this title")
+        }
+        Err(QuotaWriteError::Db(_)) => {
+            return HttpResponse::InternalServerError().body("Failed to restore todo")
+        }
+    };
+
+    audit::record(
+        &db,
+        &req,
+        NewEvent {
+            event_type: EventType::PostRestore,
+            actor_id: Some(tenant.user_id),
+            target_type: "post",
+            target_id: Some(todo.id),
+            changes: None,
+        },
+    )
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Todo restored",
+        "restored": restored
+    }))
+}
+
+// Przywrócone posty znów liczą się do limitu — sprawdzamy go w tej samej transakcji.
+// Zwraca id przywróconych postów.
+async fn untrash_posts(
+    db: &DbConn,
+    owner: &user::Model,
+    todo: &post::Model,
+    deleted_at: DateTimeWithTimeZone,
+) -> Result<Vec<i32>, QuotaWriteError> {
+    let txn = db.begin().await?;
+
+    let trashed = Entity_post::find()
+        .filter(PostColumn::OrgId.eq(todo.org_id))
+        .filter(PostColumn::UserId.eq(todo.user_id))
+        .filter(PostColumn::DeletedAt.eq(deleted_at))
+        .all(&txn)
+        .await?;
+    let ids = subtree_ids(todo.id, &trashed);
+
+    let usage = quota::locked_usage(&txn, owner).await?;
+    if usage.posts.exceeded_by(ids.len() as u64) {
+        return Err(QuotaWriteError::Exceeded(usage.posts));
+    }
+
+    Entity_post::update_many()
+        .col_expr(
+            PostColumn::DeletedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .col_expr(PostColumn::UpdatedBy, Expr::value(owner.id))
+        .col_expr(PostColumn::Version, Expr::col(PostColumn::Version).add(1))
+        .filter(PostColumn::Id.is_in(ids.clone()))
+        .filter(PostColumn::DeletedAt.eq(deleted_at))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(ids)
+}
+
+// Edytuj todo (właściciel albo współpracownik z rolą editor)
+pub async fn update_todo(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<PostUpdate>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let existing = match find_post_with_access(&db, tenant, path.into_inner(), Access::Editor).await
+    {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = precondition_failed(&req, existing.version) {
+        return response;
+    }
+    if body.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return HttpResponse::BadRequest().body("Title cannot be empty");
+    }
+
+    let before = existing.clone();
+    let mut todo: ActiveModel_todo = existing.into();
+    if let Some(title) = &body.title {
+        todo.title = Set(title.clone());
+    }
+    if let Some(content) = &body.content {
+        todo.content = Set(content.clone());
+    }
+    if let Some(format) = body.content_format {
+        todo.content_format = Set(format);
+    }
+    if body.due_at.is_some() {
+        todo.due_at = Set(body.due_at);
+    }
+    if let Some(priority) = body.priority {
+        todo.priority = Set(priority);
+    }
+
+    save_post_edit(&db, &req, tenant.user_id, before, todo).await
+}
+
+// Historia rewizji posta, od najnowszej, ze zmianami względem poprzedniej
+pub async fn list_revisions(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_post_with_access(&db, tenant, path.into_inner(), Access::Viewer).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    match todo.find_related(post_revision::Entity).all(&**db).await {
+        Ok(revisions) => HttpResponse::Ok().json(post_revision::history(revisions)),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+async fn find_revision(
+    db: &DbConn,
+    post_id: i32,
+    revision: i32,
+) -> Result<post_revision::Model, HttpResponse> {
+    match post_revision::Entity::find()
+        .filter(post_revision::Column::PostId.eq(post_id))
+        .filter(post_revision::Column::Revision.eq(revision))
+        .one(db)
+        .await
+    {
+        Ok(Some(revision)) => Ok(revision),
+        Ok(None) => Err(HttpResponse::NotFound().body("Revision not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+// Różnica między dowolnymi dwiema rewizjami posta
+pub async fn revision_diff(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<DiffQuery>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_post_with_access(&db, tenant, path.into_inner(), Access::Viewer).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    let from = match find_revision(&db, todo.id, query.from).await {
+        Ok(revision) => revision,
+        Err(response) => return response,
+    };
+    let to = match find_revision(&db, todo.id, query.to).await {
+        Ok(revision) => revision,
+        Err(response) => return response,
+    };
+    HttpResponse::Ok().json(post_revision::diff(Some(&from), &to))
+}
+
+// Przywróć tytuł, treść i jej format z wcześniejszej rewizji — przywrócenie samo staje się
+// nową rewizją
+pub async fn restore_revision(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let (post_id, revision) = path.into_inner();
+    let existing = match find_post_with_access(&db, tenant, post_id, Access::Editor).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+    if let Some(response) = precondition_failed(&req, existing.version) {
+        return response;
+    }
+    let revision = match find_revision(&db, existing.id, revision).await {
+        Ok(revision) => revision,
+        Err(response) => return response,
+    };
+
+    let before = existing.clone();
+    let mut todo: ActiveModel_todo = existing.into();
+    todo.title = Set(revision.title);
+    todo.content = Set(revision.content);
+    todo.content_format = Set(revision.content_format);
+
+    save_post_edit(&db, &req, tenant.user_id, before, todo).await
+}
+
+// Nazwy tagów dla podanych postów (post_id -> tagi)
+async fn tag_names(db: &DbConn, post_ids: Vec<i32>) -> Result<HashMap<i32, Vec<String>>, DbErr> {
+    let rows = post_tag::Entity::find()
+        .filter(post_tag::Column::PostId.is_in(post_ids))
+        .find_also_related(tag::Entity)
+        .all(db)
+        .await?;
+
+    let mut names: HashMap<i32, Vec<String>> = HashMap::new();
+    for (link, tag) in rows {
+        if let Some(tag) = tag {
+            names.entry(link.post_id).or_default().push(tag.name);
+        }
+    }
+    for tags in names.values_mut() {
+        tags.sort();
+    }
+    Ok(names)
+}
+
+// Liczba komentarzy pod każdym z podanych postów
+async fn comment_counts(db: &DbConn, post_ids: Vec<i32>) -> Result<HashMap<i32, u64>, DbErr> {
+    let counts: Vec<(i32, i64)> = comment::Entity::find()
+        .select_only()
+        .column(comment::Column::PostId)
+        .column_as(comment::Column::Id.count(), "count")
+        .filter(comment::Column::PostId.is_in(post_ids))
+        .group_by(comment::Column::PostId)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(counts
+        .into_iter()
+        .map(|(post_id, count)| (post_id, count as u64))
+        .collect())
+}
+
+// Załączniki podanych postów (z wariantami i linkami do pobrania), od najstarszego
+async fn post_attachments(
+    db: &DbConn,
+    post_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<AttachmentResponse>>, DbErr> {
+    let rows = attachment::Entity::find()
+        .filter(attachment::Column::PostId.is_in(post_ids))
+        .order_by_asc(attachment::Column::Id)
+        .find_with_related(attachment_variant::Entity)
+        .all(db)
+        .await?;
+
+    let mut by_post: HashMap<i32, Vec<AttachmentResponse>> = HashMap::new();
+    for (row, variants) in rows {
+        by_post
+            .entry(row.post_id)
+            .or_default()
+            .push(AttachmentResponse::new(row, variants));
+    }
+    Ok(by_post)
+}
+
+// Reakcje pod każdym z podanych postów, zliczone według rodzaju
+async fn reaction_counts(
+    db: &DbConn,
+    post_ids: Vec<i32>,
+) -> Result<HashMap<i32, BTreeMap<ReactionKind, u64>>, DbErr> {
+    let rows: Vec<(i32, ReactionKind, i64)> = reaction::Entity::find()
+        .select_only()
+        .column(reaction::Column::PostId)
+        .column(reaction::Column::Kind)
+        .column_as(reaction::Column::UserId.count(), "count")
+        .filter(reaction::Column::PostId.is_in(post_ids))
+        .group_by(reaction::Column::PostId)
+        .group_by(reaction::Column::Kind)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut counts: HashMap<i32, BTreeMap<ReactionKind, u64>> = HashMap::new();
+    for (post_id, kind, count) in rows {
+        counts
+            .entry(post_id)
+            .or_default()
+            .insert(kind, count as u64);
+    }
+    Ok(counts)
+}
+
+// Posty do odpowiedzi API — z danymi z powiązanych tabel, wczytanymi jednym zapytaniem
+async fn post_responses(db: &DbConn, posts: Vec<post::Model>) -> Result<Vec<PostResponse>, DbErr> {
+    let ids: Vec<i32> = posts.iter().map(|p| p.id).collect();
+    let mut tags = tag_names(db, ids.clone()).await?;
+    let comment_counts = comment_counts(db, ids.clone()).await?;
+    let mut reactions = reaction_counts(db, ids.clone()).await?;
+    let mut attachments = post_attachments(db, ids).await?;
+
+    Ok(posts
+        .into_iter()
+        .map(|post| PostResponse {
+            tags: tags.remove(&post.id).unwrap_or_default(),
+            comment_count: comment_counts.get(&post.id).copied().unwrap_or(0),
+            reactions: reactions.remove(&post.id).unwrap_or_default(),
+            attachments: attachments.remove(&post.id).unwrap_or_default(),
+            post,
+        })
+        .collect())
+}
+
+// Przypisz postowi tagi właściciela, tworząc brakujące
+async fn set_post_tags<C: ConnectionTrait>(
+    db: &C,
+    owner_id: i32,
+    post_id: i32,
+    names: &[String],
+) -> Result<(), DbErr> {
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let new_tags = names.iter().map(|name| tag::ActiveModel {
+        user_id: Set(owner_id),
+        name: Set(name.clone()),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    });
+    tag::Entity::insert_many(new_tags)
+        .on_conflict(
+            OnConflict::columns([tag::Column::UserId, tag::Column::Name])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+    let tags = tag::Entity::find()
+        .filter(tag::Column::UserId.eq(owner_id))
+        .filter(tag::Column::Name.is_in(names.to_vec()))
+        .all(db)
+        .await?;
+
+    let links = tags.into_iter().map(|tag| post_tag::ActiveModel {
+        post_id: Set(post_id),
+        tag_id: Set(tag.id),
+    });
+    post_tag::Entity::insert_many(links)
+        .on_conflict(
+            OnConflict::columns([post_tag::Column::PostId, post_tag::Column::TagId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    Ok(())
+}
+
+// Nowe wystąpienie serii dostaje te same tagi
+async fn copy_post_tags<C: ConnectionTrait>(
+    db: &C,
+    from_post: i32,
+    to_post: i32,
+) -> Result<(), DbErr> {
+    let links = post_tag::Entity::find()
+        .filter(post_tag::Column::PostId.eq(from_post))
+        .all(db)
+        .await?;
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    post_tag::Entity::insert_many(links.into_iter().map(|link| post_tag::ActiveModel {
+        post_id: Set(to_post),
+        tag_id: Set(link.tag_id),
+    }))
+    .exec_without_returning(db)
+    .await?;
+    Ok(())
+}
+
+// Posty zalogowanego użytkownika, opcjonalnie filtrowane tagami (any/all)
+pub async fn list_todos(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<PostListQuery>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    // Szkice są osobno, pod `/todos/drafts`
+    let mut select = post::find_active()
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::UserId.eq(tenant.user_id))
+        .filter(PostColumn::Draft.eq(false))
+        .order_by_desc(PostColumn::CreatedAt);
+
+    let wanted = match tag::normalize(
+        &query
+            .tags
+            .as_deref()
+            .map(|t| t.split(',').map(str::to_owned).collect::<Vec<_>>())
+            .unwrap_or_default(),
+    ) {
+        Ok(wanted) => wanted,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if !wanted.is_empty() {
+        let post_ids = match tagged_post_ids(&db, tenant, &wanted, query.tag_match).await {
+            Ok(ids) => ids,
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        };
+        select = select.filter(PostColumn::Id.is_in(post_ids));
+    }
+
+    let posts = match select.all(&**db).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    match post_responses(&db, posts).await {
+        Ok(posts) => HttpResponse::Ok().json(
+            posts
+                .into_iter()
+                .map(|p| p.with_content(query.content))
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Id postów właściciela oznaczonych którymkolwiek (Any) albo wszystkimi (All) tagami
+async fn tagged_post_ids(
+    db: &DbConn,
+    tenant: Tenant,
+    names: &[String],
+    tag_match: TagMatch,
+) -> Result<Vec<i32>, DbErr> {
+    let tag_ids: Vec<i32> = tag::Entity::find()
+        .filter(tag::Column::UserId.eq(tenant.user_id))
+        .filter(tag::Column::Id.in_subquery(tag::used_in_org(tenant.org_id)))
+        .filter(tag::Column::Name.is_in(names.to_vec()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|t| t.id)
+        .collect();
+
+    // Przy "all" brak któregokolwiek tagu oznacza pusty wynik
+    if tag_ids.is_empty() || (tag_match == TagMatch::All && tag_ids.len() < names.len()) {
+        return Ok(vec![]);
+    }
+
+    let links = post_tag::Entity::find()
+        .inner_join(Entity_post)
+        .filter(post_tag::Column::TagId.is_in(tag_ids.clone()))
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .all(db)
+        .await?;
+
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for link in links {
+        *counts.entry(link.post_id).or_default() += 1;
+    }
+
+    Ok(counts
+        .into_iter()
+        .filter(|(_, count)| tag_match == TagMatch::Any || *count == tag_ids.len())
+        .map(|(post_id, _)| post_id)
+        .collect())
+}
+
+// Podpowiedzi tagów (autocomplete) zalogowanego użytkownika — tylko tagi jego postów
+// z aktywnej organizacji
+pub async fn tag_suggestions(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<TagQuery>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    let mut select = tag::Entity::find()
+        .filter(tag::Column::UserId.eq(tenant.user_id))
+        .filter(tag::Column::Id.in_subquery(tag::used_in_org(tenant.org_id)));
+    if let Some(prefix) = query.prefix.as_deref().map(|p| p.trim().to_lowercase()) {
+        select = select.filter(Expr::col(tag::Column::Name).like(tag::prefix_pattern(&prefix)));
+    }
+
+    match select
+        .order_by_asc(tag::Column::Name)
+        .limit(query.limit.unwrap_or(10).min(100))
+        .all(&**db)
+        .await
+    {
+        Ok(tags) => HttpResponse::Ok().json(tags.into_iter().map(|t| t.name).collect::<Vec<_>>()),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Członkowie aktywnej organizacji (bez tokena — organizacji publicznych) z ich postami.
+// Administrator widzi wszystkie posty i adresy e-mail, pozostali — publiczne i własne.
+pub async fn get_users_with_posts(
+    db: web::Data<DbConn>,
+    viewer: Option<&user::Model>,
+    org_id: Option<i32>,
+) -> Result<Vec<UserWithPosts>, DbErr> {
+    let is_admin = viewer.is_some_and(|v| v.role == Role::Admin);
+
+    // Usunięci użytkownicy i posty nie są widoczne
+    let users = match org_id {
+        Some(org_id) => org_membership::find_members(org_id),
+        None => {
+            user::find_active().filter(user::Column::Id.in_subquery(organization::public_members()))
+        }
+    }
+    .all(&**db)
+    .await?;
+    let user_ids: Vec<i32> = users.iter().map(|u| u.id).collect();
+    let select = post::find_active()
+        .filter(PostColumn::UserId.is_in(user_ids))
+        .filter(match org_id {
+            Some(org_id) => PostColumn::OrgId.eq(org_id),
+            None => PostColumn::OrgId.in_subquery(organization::public_ids()),
+        })
+        .order_by_desc(PostColumn::CreatedAt);
+    let select = if is_admin {
+        select
+    } else {
+        select.filter(post::listed_for(viewer.map(|v| v.id)))
+    };
+    let posts = select.all(&**db).await?;
+
+    let mut posts_by_user: HashMap<i32, Vec<PostResponse>> = HashMap::new();
+    for response in post_responses(&db, posts).await? {
+        posts_by_user
+            .entry(response.post.user_id)
+            .or_default()
+            .push(response);
+    }
+
+    let result = users
+        .into_iter()
+        .map(|u| UserWithPosts {
+            id: u.id,
+            name: u.name,
+            lastname: u.lastname,
+            age: u.age,
+            email: is_admin.then_some(u.email),
+            created_at: u.created_at,
+            updated_at: u.updated_at,
+            posts: Some(posts_by_user.remove(&u.id).unwrap_or_default()),
+        })
+        .collect();
+
+    Ok(result)
+}
+// funkcja na określony limit czasu
+pub async fn get_users(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    // Token jest opcjonalny — bez niego widać tylko dane publiczne
+    let tenant = match viewer_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let viewer = match tenant {
+        Some(tenant) => match user::find_active_by_id(tenant.user_id).one(&**db).await {
+            Ok(viewer) => viewer,
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        },
+        None => None,
+    };
+
+    // Corrected line where we pass db as web::Data<DbConn> directly
+    match get_users_with_posts(db, viewer.as_ref(), tenant.map(|t| t.org_id)).await {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+pub async fn settings(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    // Decode token (Bearer header or cookie) and check the active organization
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    // Look for user
+    let user = match user::find_active_by_id(tenant.user_id).one(&**db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let posts = match user
+        .find_related(Entity_post)
+        .filter(PostColumn::DeletedAt.is_null())
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .order_by_desc(PostColumn::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let posts = match post_responses(&db, posts).await {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let tag = etag(user.version);
+    let result = vec![UserWithPosts {
+        id: user.id,
+        name: user.name,
+        lastname: user.lastname,
+        age: user.age,
+        email: Some(user.email),
+        created_at: user.created_at,
+        updated_at: user.updated_at,
+        posts: Some(posts),
+    }];
+
+    HttpResponse::Ok()
+        .insert_header((header::ETAG, tag))
+        .json(result)
+}
+
+pub async fn request_export(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let claims = match claims_from_request(&req) {
+        Some(claims) => claims,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let user_id = claims.sub.parse::<i32>().unwrap_or(0);
+
+    match user::find_active_by_id(user_id).one(&**db).await {
+        Ok(Some(_user)) => {}
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    }
+
+    let post_count = match post::find_active()
+        .filter(PostColumn::UserId.eq(user_id))
+        .count(&**db)
+        .await
+    {
+        Ok(count) => count,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let job = export::ActiveModel {
+        user_id: Set(user_id),
+        status: Set(ExportStatus::Pending),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    };
+    let job = match job.insert(&**db).await {
+        Ok(job) => job,
+        Err(_) => return HttpResponse::InternalServerError().body("Failed to create export"),
+    };
+
+    // Duże konta eksportujemy w tle — klient sprawdza status pod /user/export/{id}
+    if post_count > export_sync_max_posts() {
+        let status = job.status_json();
+        let db = db.get_ref().clone();
+        rt::spawn(async move { export::generate(&db, job).await });
+        return HttpResponse::Accepted().json(status);
+    }
+
+    let job_id = job.id;
+    export::generate(&db, job).await;
+    match export::Entity::find_by_id(job_id).one(&**db).await {
+        Ok(Some(job)) => HttpResponse::Created().json(job.status_json()),
+        _ => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn export_status(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let claims = match claims_from_request(&req) {
+        Some(claims) => claims,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let user_id = claims.sub.parse::<i32>().unwrap_or(0);
+
+    match export::Entity::find_by_id(path.into_inner())
+        .filter(export::Column::UserId.eq(user_id))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(job)) => HttpResponse::Ok().json(job.status_json()),
+        Ok(None) => HttpResponse::NotFound().body("Export not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Link do pobrania sam w sobie jest uprawnieniem — dlatego bez JWT, ale z datą ważności
+pub async fn download_export(db: web::Data<DbConn>, path: web::Path<String>) -> impl Responder {
+    let job = match export::Entity::find()
+        .filter(export::Column::DownloadToken.eq(path.into_inner()))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(job)) => job,
+        Ok(None) => return HttpResponse::NotFound().body("Export not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    if job.is_expired() {
+        return HttpResponse::Gone().body("Download link has expired");
+    }
+
+    let file_path = match (&job.status, &job.file_path) {
+        (ExportStatus::Ready, Some(file_path)) => file_path.clone(),
+        _ => return HttpResponse::NotFound().body("Export not found"),
+    };
+
+    match web::block(move || std::fs::read(file_path)).await {
+        Ok(Ok(bytes)) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export-{}.zip\"", job.id),
+            ))
+            .body(bytes),
+        _ => HttpResponse::InternalServerError().body("Failed to read export"),
+    }
+}
+
+// Zalogowany użytkownik z rolą administratora
+async fn current_admin(db: &DbConn, req: &HttpRequest) -> Result<user::Model, HttpResponse> {
+    let claims = match claims_from_request(req) {
+        Some(claims) => claims,
+        None => return Err(HttpResponse::Unauthorized().body("Invalid or missing token")),
+    };
+    let user_id = claims.sub.parse::<i32>().unwrap_or(0);
+
+    match user::find_active_by_id(user_id).one(db).await {
+        Ok(Some(user)) if user.role == Role::Admin => Ok(user),
+        Ok(_) => Err(HttpResponse::Forbidden().body("Admin access required")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+pub async fn audit_events(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<AuditQuery>,
+) -> impl Responder {
+    if let Err(response) = current_admin(&db, &req).await {
+        return response;
+    }
+
+    let mut select = audit::Entity::find();
+    if let Some(actor_id) = query.actor_id {
+        select = select.filter(audit::Column::ActorId.eq(actor_id));
+    }
+    if let Some(event_type) = &query.event_type {
+        select = select.filter(audit::Column::EventType.eq(event_type.clone()));
+    }
+    if let Some(from) = query.from {
+        select = select.filter(audit::Column::CreatedAt.gte(from));
+    }
+    if let Some(to) = query.to {
+        select = select.filter(audit::Column::CreatedAt.lt(to));
+    }
+
+    match select
+        .order_by_desc(audit::Column::CreatedAt)
+        .limit(query.limit.unwrap_or(100).min(1000))
+        .all(&**db)
+        .await
+    {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+async fn account_usage(db: &DbConn, user: &user::Model) -> Result<Usage, HttpResponse> {
+    quota::usage(db, user)
+        .await
+        .map_err(|_| HttpResponse::InternalServerError().body("Database error"))
+}
+
+// Zużycie limitów konta: posty, miejsce na załączniki i dzisiejsze żądania
+pub async fn usage(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let user = match user::find_active_by_id(tenant.user_id).one(&**db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    match account_usage(&db, &user).await {
+        Ok(usage) => HttpResponse::Ok().json(usage),
+        Err(response) => response,
+    }
+}
+
+// Indywidualne limity użytkownika (tylko admin); odpowiedź to jego zużycie po zmianie
+pub async fn set_quota(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<QuotaUpdate>,
+) -> impl Responder {
+    let admin = match current_admin(&db, &req).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+    let user = match user::find_active_by_id(path.into_inner()).one(&**db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let saved = quota::Entity::insert(quota::active_model(user.id, &body))
+        .on_conflict(
+            OnConflict::column(quota::Column::UserId)
+                .update_columns([
+                    quota::Column::MaxPosts,
+                    quota::Column::MaxAttachmentBytes,
+                    quota::Column::MaxRequestsPerDay,
+                    quota::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(&**db)
+        .await;
+    let saved = match saved {
+        Ok(saved) => saved,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    audit::record(
+        &db,
+        &req,
+        NewEvent {
+            event_type: EventType::UserQuotaUpdate,
+            actor_id: Some(admin.id),
+            target_type: "user",
+            target_id: Some(user.id),
+            changes: serde_json::to_value(&saved).ok(),
+        },
+    )
+    .await;
+
+    match account_usage(&db, &user).await {
+        Ok(usage) => HttpResponse::Ok().json(usage),
+        Err(response) => response,
+    }
+}
+
+// Wyszukiwanie pełnotekstowe w postach publicznych i własnych: frazy w cudzysłowach, prefiksy z `*`, filtr autora
+pub async fn search(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<SearchQuery>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let terms = search::parse_query(&query.q);
+    if terms.is_empty() {
+        return HttpResponse::BadRequest().body("Search query is empty");
+    }
+
+    match search::search_posts(
+        &db,
+        &terms,
+        tenant,
+        query.author,
+        query.limit.unwrap_or(20).clamp(1, 100),
+        query.offset.unwrap_or(0),
+    )
+    .await
+    {
+        Ok(hits) => HttpResponse::Ok().json(hits),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Organizacje, których posty może zobaczyć `viewer`: aktywna z tokena albo — bez tokena —
+// publiczne. Dane nigdy nie przechodzą między organizacjami.
+fn visible_orgs(viewer: Option<Tenant>) -> Condition {
+    match viewer {
+        Some(viewer) => Condition::all().add(PostColumn::OrgId.eq(viewer.org_id)),
+        None => Condition::all().add(PostColumn::OrgId.in_subquery(organization::public_ids())),
+    }
+}
+
+// Aktywny post, który `viewer` może otworzyć: nieprywatny, własny albo udostępniony
+// (cudzy prywatny wygląda jak nieistniejący). Zalogowany widzi tylko posty swojej
+// aktywnej organizacji, niezalogowany — nieprywatne posty organizacji publicznych.
+async fn find_visible_post(
+    db: &DbConn,
+    viewer: Option<Tenant>,
+    post_id: i32,
+) -> Result<post::Model, HttpResponse> {
+    let select = post::find_active()
+        .filter(PostColumn::Id.eq(post_id))
+        .filter(visible_orgs(viewer));
+    let post = match select.one(db).await {
+        Ok(Some(post)) => post,
+        Ok(None) => return Err(HttpResponse::NotFound().body("Post not found")),
+        Err(_) => return Err(HttpResponse::InternalServerError().body("Database error")),
+    };
+    if post.is_visible_to(viewer.map(|v| v.user_id)) {
+        return Ok(post);
+    }
+
+    match viewer {
+        Some(viewer) => match post_access(db, viewer.user_id, &post).await {
+            Ok(Some(_)) => Ok(post),
+            Ok(None) => Err(HttpResponse::NotFound().body("Post not found")),
+            Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+        },
+        None => Err(HttpResponse::NotFound().body("Post not found")),
+    }
+}
+
+async fn find_comment(
+    db: &DbConn,
+    post_id: i32,
+    comment_id: i32,
+) -> Result<comment::Model, HttpResponse> {
+    match comment::Entity::find_by_id(comment_id)
+        .filter(comment::Column::PostId.eq(post_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(comment)) => Ok(comment),
+        Ok(None) => Err(HttpResponse::NotFound().body("Comment not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+// Komentarze posta ułożone w wątki
+pub async fn list_comments(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let viewer = match viewer_tenant(&db, &req).await {
+        Ok(viewer) => viewer,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let post = match find_visible_post(&db, viewer, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    match post.find_related(comment::Entity).all(&**db).await {
+        Ok(comments) => HttpResponse::Ok().json(comment::build_threads(comments)),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn add_comment(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<CommentCreate>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let post = match find_visible_post(&db, Some(tenant), path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    let content = body.content.trim();
+    if content.is_empty() {
+        return HttpResponse::BadRequest().body("Comment cannot be empty");
+    }
+
+    // Odpowiedź musi dotyczyć komentarza pod tym samym postem
+    if let Some(parent_id) = body.parent_id
+        && let Err(response) = find_comment(&db, post.id, parent_id).await
+    {
+        return response;
+    }
+
+    let new_comment = comment::ActiveModel {
+        post_id: Set(post.id),
+        user_id: Set(tenant.user_id),
+        parent_id: Set(body.parent_id),
+        content: Set(content.to_owned()),
+        ..Default::default()
+    };
+
+    match new_comment.insert(&**db).await {
+        Ok(comment) => HttpResponse::Created().json(comment),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Treść komentarza może zmienić tylko jego autor
+pub async fn update_comment(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<CommentUpdate>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let (post_id, comment_id) = path.into_inner();
+    let post = match find_visible_post(&db, Some(tenant), post_id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    let comment = match find_comment(&db, post.id, comment_id).await {
+        Ok(comment) => comment,
+        Err(response) => return response,
+    };
+
+    if comment.user_id != tenant.user_id {
+        return HttpResponse::Forbidden().body("Only the author can edit a comment");
+    }
+
+    let content = body.content.trim();
+    if content.is_empty() {
+        return HttpResponse::BadRequest().body("Comment cannot be empty");
+    }
+
+    let mut active: comment::ActiveModel = comment.into();
+    active.content = Set(content.to_owned());
+    match active.update(&**db).await {
+        Ok(comment) => HttpResponse::Ok().json(comment),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Usunąć komentarz (razem z odpowiedziami) może autor albo właściciel posta (moderacja)
+pub async fn delete_comment(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let (post_id, comment_id) = path.into_inner();
+    let post = match find_visible_post(&db, Some(tenant), post_id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    let comment = match find_comment(&db, post.id, comment_id).await {
+        Ok(comment) => comment,
+        Err(response) => return response,
+    };
+
+    if comment.user_id != tenant.user_id && post.user_id != tenant.user_id {
+        return HttpResponse::Forbidden()
+            .body("Only the author or the post owner can delete a comment");
+    }
+
+    match comment.delete(&**db).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Ile plików można wysłać w jednym żądaniu
+const MAX_FILES_PER_UPLOAD: usize = 10;
+
+// Wgraj pliki (multipart, pola z nazwą pliku) do posta; wymaga roli editor.
+// Najpierw sprawdzamy wszystkie pliki, a odrzucone żądanie usuwa z magazynu to, co zapisało.
+pub async fn upload_attachments(
+    db: web::Data<DbConn>,
+    storage: web::Data<dyn Storage>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    mut payload: Multipart,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_post_with_access(&db, tenant, path.into_inner(), Access::Editor).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    let max_bytes = attachment_max_bytes();
+    let mut files: Vec<(String, String, Bytes, Option<VariantsStatus>)> = vec![];
+    while let Some(field) = payload.next().await {
+        let mut field = match field {
+            Ok(field) => field,
+            Err(_) => return HttpResponse::BadRequest().body("Invalid multipart body"),
+        };
+        // Zwykłe pola formularza (bez nazwy pliku) pomijamy
+        let file_name = match field.content_disposition().and_then(|cd| cd.get_filename()) {
+            Some(name) => attachment::clean_file_name(name),
+            None => continue,
+        };
+        if files.len() == MAX_FILES_PER_UPLOAD {
+            return HttpResponse::BadRequest().body(format!(
+                "At most {} files can be uploaded at once",
+                MAX_FILES_PER_UPLOAD
+            ));
+        }
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(_) => return HttpResponse::BadRequest().body("Invalid multipart body"),
+            };
+            if data.len() + chunk.len() > max_bytes {
+                return HttpResponse::PayloadTooLarge()
+                    .body(format!("Files can be at most {} bytes", max_bytes));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        if data.is_empty() {
+            return HttpResponse::BadRequest().body("Empty files cannot be attached");
+        }
+        if attachment::is_executable(&data) {
+            return HttpResponse::UnsupportedMediaType()
+                .body("Executable files cannot be attached");
+        }
+
+        let content_type = attachment::sniff_content_type(&data);
+        let mut data = data.freeze();
+        // Metadane obrazka (np. położenie z EXIF) usuwamy, zanim plik trafi do magazynu.
+        // Obrazka, którego nie umiemy oczyścić, nie przyjmujemy — nie dałoby się go pobrać.
+        let variants_status = if content_type.starts_with("image/") {
+            match thumbnail::clean_upload(&data, &content_type) {
+                Cleaned::Unsupported => {
+                    return HttpResponse::UnsupportedMediaType().body(format!(
+                        "{} images cannot be attached, or the file is damaged",
+                        content_type
+                    ))
+                }
+                cleaned => {
+                    if let Cleaned::Stripped(stripped) = cleaned {
+                        data = Bytes::from(stripped);
+                    }
+                    thumbnail::is_supported(&content_type).then_some(VariantsStatus::Pending)
+                }
+            }
+        } else {
+            None
+        };
+        files.push((file_name, content_type, data, variants_status));
+    }
+    if files.is_empty() {
+        return HttpResponse::BadRequest().body("No files in the upload");
+    }
+
+    // Limit miejsca liczymy dla wgrywającego, nie właściciela posta
+    let uploader = match user::find_active_by_id(tenant.user_id).one(&**db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    // Wstępne sprawdzenie bez blokady, żeby nie wysyłać do magazynu plików, które i tak się
+    // nie zmieszczą; wiążące jest to w transakcji zapisu
+    let upload_bytes: u64 = files.iter().map(|(_, _, data, _)| data.len() as u64).sum();
+    match quota::usage(&**db, &uploader).await {
+        Ok(usage) if usage.attachment_bytes.exceeded_by(upload_bytes) => {
+            return attachment_quota_exceeded(&usage.attachment_bytes)
+        }
+        Ok(_) => {}
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    }
+
+    // Pliki zapisujemy przed transakcją — blokada wiersza użytkownika nie może czekać
+    // na magazyn (S3 to żądania sieciowe)
+    let mut rows = vec![];
+    let mut stored_keys = vec![];
+    for (file_name, content_type, data, variants_status) in files {
+        let storage_key = format!("posts/{}/{}", todo.id, random_token(32));
+        let size_bytes = data.len() as i64;
+        if let Err(e) = storage.put(&storage_key, data, &content_type).await {
+            eprintln!("Failed to store attachment: {}", e);
+            remove_stored_files(&**storage, &stored_keys).await;
+            return HttpResponse::InternalServerError().body("Failed to store file");
+        }
+        stored_keys.push(storage_key.clone());
+
+        rows.push(attachment::ActiveModel {
+            post_id: Set(todo.id),
+            user_id: Set(tenant.user_id),
+            file_name: Set(file_name),
+            content_type: Set(content_type),
+            size_bytes: Set(size_bytes),
+            storage_key: Set(storage_key),
+            // Miniatury obrazków wygeneruje zadanie w tle
+            variants_status: Set(variants_status),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        });
+    }
+
+    match insert_attachments(&db, &uploader, rows, upload_bytes).await {
+        Ok(saved) => HttpResponse::Created().json(
+            saved
+                .into_iter()
+                .map(|row| AttachmentResponse::new(row, vec![]))
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => {
+            remove_stored_files(&**storage, &stored_keys).await;
+            match e {
+                QuotaWriteError::Exceeded(bytes) => attachment_quota_exceeded(&bytes),
+                QuotaWriteError::Db(_) => {
+                    HttpResponse::InternalServerError().body("Database error")
+                }
+            }
+        }
+    }
+}
+
+// Krótka transakcja: sprawdzenie limitu pod blokadą (patrz `quota::locked_usage`) i wiersze
+// plików, które są już w magazynie
+async fn insert_attachments(
+    db: &DbConn,
+    uploader: &user::Model,
+    rows: Vec<attachment::ActiveModel>,
+    upload_bytes: u64,
+) -> Result<Vec<attachment::Model>, QuotaWriteError> {
+    let txn = db.begin().await?;
+    let usage = quota::locked_usage(&txn, uploader).await?;
+    if usage.attachment_bytes.exceeded_by(upload_bytes) {
+        return Err(QuotaWriteError::Exceeded(usage.attachment_bytes));
+    }
+    let mut saved = vec![];
+    for row in rows {
+        saved.push(row.insert(&txn).await?);
+    }
+    txn.commit().await?;
+    Ok(saved)
+}
+
+fn attachment_quota_exceeded(bytes: &Meter) -> HttpResponse {
+    HttpResponse::Forbidden().body(format!(
+        "Attachment storage quota exceeded: {} of {} bytes used",
+        bytes.used,
+        bytes.limit.unwrap_or_default()
+    ))
+}
+
+// Bez wierszy w bazie pliki byłyby nieosiągalne
+async fn remove_stored_files(storage: &dyn Storage, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = storage.delete(key).await {
+            eprintln!("Failed to remove orphaned attachment: {}", e);
+        }
+    }
+}
+
+// Załączniki posta z podpisanymi linkami do pobrania
+pub async fn list_attachments(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_post_with_access(&db, tenant, path.into_inner(), Access::Viewer).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    match post_attachments(&db, vec![todo.id]).await {
+        Ok(mut attachments) => {
+            HttpResponse::Ok().json(attachments.remove(&todo.id).unwrap_or_default())
+        }
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn delete_attachment(
+    db: web::Data<DbConn>,
+    storage: web::Data<dyn Storage>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let (post_id, attachment_id) = path.into_inner();
+    let todo = match find_post_with_access(&db, tenant, post_id, Access::Editor).await {
+        Ok((post, _)) => post,
+        Err(response) => return response,
+    };
+
+    let file = match attachment::Entity::find_by_id(attachment_id)
+        .filter(attachment::Column::PostId.eq(todo.id))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(file)) => file,
+        Ok(None) => return HttpResponse::NotFound().body("Attachment not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let mut storage_keys = match file
+        .find_related(attachment_variant::Entity)
+        .all(&**db)
+        .await
+    {
+        Ok(variants) => variants
+            .into_iter()
+            .map(|v| v.storage_key)
+            .collect::<Vec<_>>(),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    storage_keys.push(file.storage_key.clone());
+
+    // Warianty znikną razem z wierszem (kaskada)
+    if file.delete(&**db).await.is_err() {
+        return HttpResponse::InternalServerError().body("Database error");
+    }
+    // Wiersz już zniknął, więc pliki i tak są nieosiągalne — błędy tylko logujemy
+    for storage_key in storage_keys {
+        if let Err(e) = storage.delete(&storage_key).await {
+            eprintln!("Failed to remove attachment file: {}", e);
+        }
+    }
+    HttpResponse::NoContent().finish()
+}
+
+// Pobranie pliku przez podpisany link (bez tokena — link sam jest uprawnieniem)
+pub async fn download_attachment(
+    db: web::Data<DbConn>,
+    storage: web::Data<dyn Storage>,
+    path: web::Path<i32>,
+    query: web::Query<DownloadQuery>,
+) -> impl Responder {
+    let attachment_id = path.into_inner();
+    if !attachment::verify_download(attachment_id, &query) {
+        return HttpResponse::Forbidden().body("Invalid or expired download link");
+    }
+
+    // Pliki miękko usuniętych postów nie są dostępne
+    let file = match attachment::Entity::find_by_id(attachment_id)
+        .inner_join(Entity_post)
+        .filter(PostColumn::DeletedAt.is_null())
+        .one(&**db)
+        .await
+    {
+        Ok(Some(file)) => file,
+        Ok(None) => return HttpResponse::NotFound().body("Attachment not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    // Wariant (np. miniatura) zamiast oryginału: "zdjecie.jpg" → "zdjecie-thumb.jpg"
+    let (storage_key, content_type, file_name) = match &query.variant {
+        // Oryginał, z którego nie usunęliśmy metadanych, zostaje u nas
+        None if file.variants_status == Some(VariantsStatus::Failed) => {
+            return HttpResponse::Forbidden()
+                .body("The original of this image is not available for download")
+        }
+        None => (file.storage_key, file.content_type, file.file_name),
+        Some(name) => match file
+            .find_related(attachment_variant::Entity)
+            .filter(attachment_variant::Column::Variant.eq(name.as_str()))
+            .one(&**db)
+            .await
+        {
+            Ok(Some(variant)) => {
+                let stem = file
+                    .file_name
+                    .rsplit_once('.')
+                    .map_or(file.file_name.as_str(), |(stem, _)| stem);
+                let extension = variant.storage_key.rsplit('.').next().unwrap_or_default();
+                let file_name = format!("{}-{}.{}", stem, variant.variant, extension);
+                (variant.storage_key, variant.content_type, file_name)
+            }
+            Ok(None) => return HttpResponse::NotFound().body("Attachment variant not found"),
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        },
+    };
+
+    match storage.get(&storage_key).await {
+        Ok(Some(data)) => HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(ContentDisposition::attachment(file_name))
+            .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .body(data),
+        Ok(None) => HttpResponse::NotFound().body("Attachment not found"),
+        Err(e) => {
+            eprintln!("Failed to read attachment: {}", e);
+            HttpResponse::InternalServerError().body("Failed to read attachment")
+        }
+    }
+}
+
+// Dodaj reakcję, a jeśli już jest — cofnij ją
+pub async fn toggle_reaction(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<(i32, ReactionKind)>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let (post_id, kind) = path.into_inner();
+    let post = match find_visible_post(&db, Some(tenant), post_id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    let removed = match reaction::Entity::delete_by_id((post.id, tenant.user_id, kind))
+        .exec(&**db)
+        .await
+    {
+        Ok(res) => res.rows_affected > 0,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    if !removed {
+        // Równoległe kliknięcie mogło już dodać tę samą reakcję — wtedy nic nie robimy
+        let new_reaction = reaction::ActiveModel {
+            post_id: Set(post.id),
+            user_id: Set(tenant.user_id),
+            kind: Set(kind),
+            created_at: Set(Utc::now().into()),
+        };
+        if reaction::Entity::insert(new_reaction)
+            .on_conflict(
+                OnConflict::columns([
+                    reaction::Column::PostId,
+                    reaction::Column::UserId,
+                    reaction::Column::Kind,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(&**db)
+            .await
+            .is_err()
+        {
+            return HttpResponse::InternalServerError().body("Database error");
+        }
+    }
+
+    match reaction_counts(&db, vec![post.id]).await {
+        Ok(mut counts) => HttpResponse::Ok().json(serde_json::json!({
+            "reacted": !removed,
+            "reactions": counts.remove(&post.id).unwrap_or_default()
+        })),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Posty, na które zalogowany użytkownik zareagował — ostatnio polubione najpierw
+pub async fn reacted_todos(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    query: web::Query<ReactedQuery>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    let mut select = reaction::Entity::find()
+        .filter(reaction::Column::UserId.eq(tenant.user_id))
+        .order_by_desc(reaction::Column::CreatedAt);
+    if let Some(kind) = query.kind {
+        select = select.filter(reaction::Column::Kind.eq(kind));
+    }
+    let reactions = match select.all(&**db).await {
+        Ok(reactions) => reactions,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    // Kilka reakcji na ten sam post — liczy się najnowsza
+    let mut seen = HashSet::new();
+    let post_ids: Vec<i32> = reactions
+        .iter()
+        .map(|r| r.post_id)
+        .filter(|id| seen.insert(*id))
+        .collect();
+
+    let mut posts: HashMap<i32, post::Model> = match post::find_active()
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::Id.is_in(post_ids.clone()))
+        .all(&**db)
+        .await
+    {
+        Ok(posts) => posts.into_iter().map(|p| (p.id, p)).collect(),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    // Post mógł w międzyczasie stać się prywatny albo przestać być udostępniony
+    let mut hidden = vec![];
+    for post in posts.values() {
+        if post.is_visible_to(Some(tenant.user_id)) {
+            continue;
+        }
+        match post_access(&db, tenant.user_id, post).await {
+            Ok(Some(_)) => {}
+            Ok(None) => hidden.push(post.id),
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        }
+    }
+    for id in hidden {
+        posts.remove(&id);
+    }
+    let ordered = post_ids
+        .into_iter()
+        .filter_map(|id| posts.remove(&id))
+        .collect();
+
+    match post_responses(&db, ordered).await {
+        Ok(posts) => HttpResponse::Ok().json(posts),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Pojedynczy post: każdy nieprywatny (publiczny albo z linku), prywatny tylko dla właściciela
+pub async fn get_post(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<ContentQuery>,
+) -> impl Responder {
+    let viewer = match viewer_tenant(&db, &req).await {
+        Ok(viewer) => viewer,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let post = match find_visible_post(&db, viewer, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    let tag = etag(post.version);
+    match post_responses(&db, vec![post]).await {
+        Ok(mut posts) => HttpResponse::Ok()
+            .insert_header((header::ETAG, tag))
+            .json(posts.remove(0).with_content(query.content)),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Publiczny profil użytkownika
+pub async fn public_profile(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    // Zalogowany widzi członków swojej organizacji, niezalogowany — organizacji publicznych
+    let viewer = match viewer_tenant(&db, &req).await {
+        Ok(viewer) => viewer,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let members = match viewer {
+        Some(viewer) => org_membership::find_members(viewer.org_id),
+        None => {
+            user::find_active().filter(user::Column::Id.in_subquery(organization::public_members()))
+        }
+    };
+    let user = match members
+        .filter(user::Column::Id.eq(path.into_inner()))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let posts = match post::find_active()
+        .filter(PostColumn::UserId.eq(user.id))
+        .filter(visible_orgs(viewer))
+        .filter(PostColumn::Visibility.eq(Visibility::Public))
+        .filter(PostColumn::Draft.eq(false))
+        .order_by_desc(PostColumn::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    match post_responses(&db, posts).await {
+        Ok(posts) => HttpResponse::Ok().json(PublicProfile {
+            id: user.id,
+            name: user.name,
+            lastname: user.lastname,
+            created_at: user.created_at,
+            posts,
+        }),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn set_visibility(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<SetVisibility>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_own_post(&db, tenant, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    if let Some(response) = precondition_failed(&req, todo.version) {
+        return response;
+    }
+
+    let mut active: ActiveModel_todo = todo.into();
+    active.visibility = Set(body.visibility);
+    active.updated_by = Set(Some(tenant.user_id));
+    match update_versioned(active, &**db, PostColumn::Version).await {
+        Ok(saved) => {
+            search_index::sync_posts(vec![saved.clone()]).await;
+            HttpResponse::Ok()
+                .insert_header((header::ETAG, etag(saved.version)))
+                .json(saved)
+        }
+        Err(e) if version::is_conflict(&e) => {
+            HttpResponse::PreconditionFailed().body("Resource has been modified")
+        }
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Własne szkice w bieżącej organizacji: najpierw zaplanowane (najbliższe na górze),
+// potem bez terminu
+pub async fn list_drafts(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    let drafts = match post::find_active()
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::UserId.eq(tenant.user_id))
+        .filter(PostColumn::Draft.eq(true))
+        .order_by_asc(PostColumn::PublishAt)
+        .order_by_desc(PostColumn::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(drafts) => drafts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    match post_responses(&db, drafts).await {
+        Ok(drafts) => HttpResponse::Ok().json(drafts),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Ustaw albo przesuń termin publikacji szkicu
+pub async fn schedule_draft(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<SchedulePost>,
+) -> impl Responder {
+    if body.publish_at <= Utc::now() {
+        return HttpResponse::BadRequest().body("publish_at must be in the future");
+    }
+    save_draft(&db, &req, path.into_inner(), true, Some(body.publish_at)).await
+}
+
+// Zdejmij termin — post zostaje szkicem, dopóki ktoś go nie opublikuje
+pub async fn unschedule_draft(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    save_draft(&db, &req, path.into_inner(), true, None).await
+}
+
+// Opublikuj szkic od razu
+pub async fn publish_draft(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    save_draft(&db, &req, path.into_inner(), false, Some(Utc::now().into())).await
+}
+
+// Szkicem zarządza tylko autor; opublikowanego posta nie da się cofnąć do szkicu
+async fn save_draft(
+    db: &DbConn,
+    req: &HttpRequest,
+    post_id: i32,
+    draft: bool,
+    publish_at: Option<DateTimeWithTimeZone>,
+) -> HttpResponse {
+    let tenant = match current_tenant(db, req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_own_post(db, tenant, post_id).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+    if !todo.draft {
+        return HttpResponse::Conflict().body("Post is already published");
+    }
+
+    if let Some(response) = precondition_failed(req, todo.version) {
+        return response;
+    }
+
+    let mut active: ActiveModel_todo = todo.into();
+    active.draft = Set(draft);
+    active.publish_at = Set(publish_at);
+    active.updated_by = Set(Some(tenant.user_id));
+    let saved = match update_versioned(active, db, PostColumn::Version).await {
+        Ok(saved) => saved,
+        Err(e) if version::is_conflict(&e) => {
+            return HttpResponse::PreconditionFailed().body("Resource has been modified");
+        }
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    search_index::sync_posts(vec![saved.clone()]).await;
+
+    let tag = etag(saved.version);
+    match post_responses(db, vec![saved]).await {
+        Ok(mut posts) => HttpResponse::Ok()
+            .insert_header((header::ETAG, tag))
+            .json(posts.remove(0)),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Udostępnij własny post innemu użytkownikowi; ponowne zaproszenie zmienia rolę
+pub async fn share_post(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<ShareCreate>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_own_post(&db, tenant, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    // Udostępniać można tylko członkom organizacji, do której należy post — a tych
+    // udostępniający i tak widzi na liście członków
+    let invitee = match org_membership::find_members(todo.org_id)
+        .filter(user::Column::Email.eq(body.email.trim()))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    if invitee.id == tenant.user_id {
+        return HttpResponse::BadRequest().body("You cannot share a post with yourself");
+    }
+
+    let existing = match post_share::Entity::find()
+        .filter(post_share::Column::PostId.eq(todo.id))
+        .filter(post_share::Column::UserId.eq(invitee.id))
+        .one(&**db)
+        .await
+    {
+        Ok(existing) => existing,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let (result, created) = match existing {
+        Some(share) => {
+            let mut share: post_share::ActiveModel = share.into();
+            share.role = Set(body.role);
+            (share.update(&**db).await, false)
+        }
+        None => {
+            let share = post_share::ActiveModel {
+                post_id: Set(todo.id),
+                user_id: Set(invitee.id),
+                role: Set(body.role),
+                invited_by: Set(tenant.user_id),
+                accepted_at: Set(None),
+                created_at: Set(Utc::now().into()),
+                ..Default::default()
+            };
+            (share.insert(&**db).await, true)
+        }
+    };
+
+    match result {
+        Ok(share) if created => HttpResponse::Created().json(share),
+        Ok(share) => HttpResponse::Ok().json(share),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Udostępnienia własnego posta (przyjęte i oczekujące)
+pub async fn list_shares(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let todo = match find_own_post(&db, tenant, path.into_inner()).await {
+        Ok(post) => post,
+        Err(response) => return response,
+    };
+
+    match todo
+        .find_related(post_share::Entity)
+        .order_by_asc(post_share::Column::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(shares) => HttpResponse::Ok().json(shares),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Cofnij udostępnienie: właściciel posta albo sam współpracownik (rezygnacja)
+pub async fn revoke_share(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let (post_id, grantee_id) = path.into_inner();
+
+    let todo = match post::find_active()
+        .filter(PostColumn::Id.eq(post_id))
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(post)) => post,
+        Ok(None) => return HttpResponse::NotFound().body("Post not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    if todo.user_id != tenant.user_id && grantee_id != tenant.user_id {
+        return HttpResponse::NotFound().body("Share not found");
+    }
+
+    match post_share::Entity::delete_many()
+        .filter(post_share::Column::PostId.eq(todo.id))
+        .filter(post_share::Column::UserId.eq(grantee_id))
+        .exec(&**db)
+        .await
+    {
+        Ok(res) if res.rows_affected > 0 => HttpResponse::NoContent().finish(),
+        Ok(_) => HttpResponse::NotFound().body("Share not found"),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Oczekujące zaproszenia zalogowanego użytkownika
+pub async fn share_invitations(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    match post_share::Entity::find()
+        .filter(post_share::Column::UserId.eq(tenant.user_id))
+        .filter(post_share::Column::AcceptedAt.is_null())
+        .find_also_related(Entity_post)
+        .filter(PostColumn::DeletedAt.is_null())
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .order_by_desc(post_share::Column::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .map(|(share, post)| {
+                    serde_json::json!({
+                        "invitation": share,
+                        "todo": post.map(|p| serde_json::json!({ "id": p.id, "title": p.title }))
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Oczekujące zaproszenie do posta z aktywnej organizacji
+async fn find_invitation(
+    db: &DbConn,
+    tenant: Tenant,
+    share_id: i32,
+) -> Result<post_share::Model, HttpResponse> {
+    match post_share::Entity::find_by_id(share_id)
+        .filter(post_share::Column::UserId.eq(tenant.user_id))
+        .filter(post_share::Column::AcceptedAt.is_null())
+        .inner_join(Entity_post)
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(share)) => Ok(share),
+        Ok(None) => Err(HttpResponse::NotFound().body("Invitation not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+pub async fn accept_invitation(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let share = match find_invitation(&db, tenant, path.into_inner()).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+
+    let mut share: post_share::ActiveModel = share.into();
+    share.accepted_at = Set(Some(Utc::now().into()));
+    match share.update(&**db).await {
+        Ok(share) => HttpResponse::Ok().json(share),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn decline_invitation(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+    let share = match find_invitation(&db, tenant, path.into_inner()).await {
+        Ok(share) => share,
+        Err(response) => return response,
+    };
+
+    match share.delete(&**db).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Posty udostępnione zalogowanemu użytkownikowi, razem z jego rolą
+pub async fn shared_with_me(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let tenant = match current_tenant(&db, &req).await {
+        Ok(tenant) => tenant,
+        Err(response) => return response,
+    };
+
+    let shares = match post_share::find_accepted()
+        .filter(post_share::Column::UserId.eq(tenant.user_id))
+        .all(&**db)
+        .await
+    {
+        Ok(shares) => shares,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+    let roles: HashMap<i32, post_share::ShareRole> =
+        shares.iter().map(|s| (s.post_id, s.role)).collect();
+
+    let posts = match post::find_active()
+        .filter(PostColumn::OrgId.eq(tenant.org_id))
+        .filter(PostColumn::Id.is_in(roles.keys().copied().collect::<Vec<_>>()))
+        .order_by_desc(PostColumn::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(posts) => posts,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    match post_responses(&db, posts).await {
+        Ok(posts) => HttpResponse::Ok().json(
+            posts
+                .into_iter()
+                .map(|post| SharedPost {
+                    role: roles[&post.post.id],
+                    post,
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Organizacje zalogowanego użytkownika z jego rolą; działa też po usunięciu z aktywnej
+pub async fn list_orgs(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let claims = match claims_from_request(&req) {
+        Some(claims) => claims,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let user_id = claims.sub.parse::<i32>().unwrap_or(0);
+
+    match org_membership::find_accepted()
+        .filter(org_membership::Column::UserId.eq(user_id))
+        .find_also_related(organization::Entity)
+        .order_by_asc(org_membership::Column::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .filter_map(|(membership, org)| {
+                    org.map(|org| OrgSummary {
+                        active: org.id == claims.org,
+                        role: membership.role,
+                        org,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Oczekujące zaproszenia zalogowanego użytkownika do organizacji
+pub async fn org_invitations(db: web::Data<DbConn>, req: HttpRequest) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+
+    match org_membership::Entity::find()
+        .filter(org_membership::Column::UserId.eq(user_id))
+        .filter(org_membership::Column::AcceptedAt.is_null())
+        .find_also_related(organization::Entity)
+        .order_by_desc(org_membership::Column::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .filter_map(|(membership, org)| {
+                    org.map(|org| {
+                        serde_json::json!({
+                            "org_id": org.id,
+                            "name": org.name,
+                            "role": membership.role,
+                            "invited_at": membership.created_at
+                        })
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Oczekujące zaproszenie zalogowanego użytkownika do organizacji `org_id`
+async fn find_org_invitation(
+    db: &DbConn,
+    req: &HttpRequest,
+    org_id: i32,
+) -> Result<org_membership::Model, HttpResponse> {
+    let user_id = match current_user_id(req) {
+        Some(id) => id,
+        None => return Err(HttpResponse::Unauthorized().body("Invalid or missing token")),
+    };
+
+    match org_membership::Entity::find_by_id((org_id, user_id))
+        .filter(org_membership::Column::AcceptedAt.is_null())
+        .one(db)
+        .await
+    {
+        Ok(Some(membership)) => Ok(membership),
+        Ok(None) => Err(HttpResponse::NotFound().body("Invitation not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+pub async fn accept_org_invitation(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let membership = match find_org_invitation(&db, &req, path.into_inner()).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+
+    let mut membership: org_membership::ActiveModel = membership.into();
+    membership.accepted_at = Set(Some(Utc::now().into()));
+    match membership.update(&**db).await {
+        Ok(membership) => HttpResponse::Ok().json(membership),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn decline_org_invitation(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let membership = match find_org_invitation(&db, &req, path.into_inner()).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+
+    match membership.delete(&**db).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+pub async fn create_org(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    body: web::Json<OrgCreate>,
+) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+
+    let name = body.name.trim();
+    if name.is_empty() {
+        return HttpResponse::BadRequest().body("Organization name cannot be empty");
+    }
+
+    match organization::create_with_owner(&db, name, body.public.unwrap_or(false), user_id).await {
+        Ok(org) => HttpResponse::Created().json(org),
+        Err(_) => HttpResponse::InternalServerError().body("Failed to create organization"),
+    }
+}
+
+// Otwórz organizację dla niezalogowanych albo ją zamknij (tylko właściciel)
+pub async fn set_org_public(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<SetOrgPublic>,
+) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let membership = match find_membership(&db, path.into_inner(), user_id).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+    if membership.role != OrgRole::Owner {
+        return HttpResponse::Forbidden().body("Only owners can change organization visibility");
+    }
+
+    let org = organization::ActiveModel {
+        id: Set(membership.org_id),
+        public: Set(body.public),
+        ..Default::default()
+    };
+    match org.update(&**db).await {
+        Ok(org) => HttpResponse::Ok().json(org),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Przyjęte członkostwo użytkownika w organizacji; organizacja bez niego wygląda jak nieistniejąca
+async fn find_membership(
+    db: &DbConn,
+    org_id: i32,
+    user_id: i32,
+) -> Result<org_membership::Model, HttpResponse> {
+    match org_membership::find_accepted()
+        .filter(org_membership::Column::OrgId.eq(org_id))
+        .filter(org_membership::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(membership)) => Ok(membership),
+        Ok(None) => Err(HttpResponse::NotFound().body("Organization not found")),
+        Err(_) => Err(HttpResponse::InternalServerError().body("Database error")),
+    }
+}
+
+// Przełącz aktywną organizację — wydajemy nowy token (tam, skąd przyszedł stary)
+pub async fn switch_org(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let membership = match find_membership(&db, path.into_inner(), user_id).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+
+    let token = generate_jwt(&user_id.to_string(), membership.org_id);
+    if let Some((_, TokenSource::Cookie)) = request_token(&req) {
+        let csrf_token = generate_csrf_token();
+        let (auth_cookie, csrf_cookie) = auth_cookies(&token, &csrf_token);
+        return HttpResponse::Ok()
+            .cookie(auth_cookie)
+            .cookie(csrf_cookie)
+            .json(serde_json::json!({
+                "csrf_token": csrf_token,
+                "org_id": membership.org_id
+            }));
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "token": token,
+        "org_id": membership.org_id
+    }))
+}
+
+pub async fn list_members(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let membership = match find_membership(&db, path.into_inner(), user_id).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+
+    // Oczekujące zaproszenia nie są członkostwem — nie pokazujemy ich
+    match org_membership::find_accepted()
+        .filter(org_membership::Column::OrgId.eq(membership.org_id))
+        .find_also_related(user::Entity)
+        .filter(user::Column::DeletedAt.is_null())
+        .order_by_asc(org_membership::Column::CreatedAt)
+        .all(&**db)
+        .await
+    {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .filter_map(|(member, user)| {
+                    user.map(|user| OrgMember {
+                        user_id: user.id,
+                        name: user.name,
+                        lastname: user.lastname,
+                        role: member.role,
+                        joined_at: member.created_at,
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Zaproś istniejącego użytkownika do organizacji (właściciel albo administrator);
+// członkiem zostaje dopiero po przyjęciu zaproszenia
+pub async fn add_member(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<MemberAdd>,
+) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let actor = match find_membership(&db, path.into_inner(), user_id).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+
+    if actor.role < OrgRole::Admin {
+        return HttpResponse::Forbidden().body("Only owners and admins can manage members");
+    }
+    if body.role == OrgRole::Owner && actor.role != OrgRole::Owner {
+        return HttpResponse::Forbidden().body("Only owners can add owners");
+    }
+
+    // Ta sama odpowiedź dla nieznanego adresu, nowego i dotychczasowego członka —
+    // inaczej dałoby się sprawdzać, kto ma konto. Zaproszenia nie widać na liście członków.
+    let invited = HttpResponse::Accepted().json(serde_json::json!({
+        "message": "If this email belongs to a registered user, they have been invited"
+    }));
+    let invitee = match user::find_active()
+        .filter(user::Column::Email.eq(body.email.trim()))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return invited,
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    let membership = org_membership::ActiveModel {
+        org_id: Set(actor.org_id),
+        user_id: Set(invitee.id),
+        role: Set(body.role),
+        created_at: Set(Utc::now().into()),
+        accepted_at: Set(None),
+    };
+    match membership.insert(&**db).await {
+        Ok(_) => invited,
+        Err(e) if is_unique_violation(&e) => invited,
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
+}
+
+// Usuń członka (właściciel albo administrator) albo opuść organizację samemu.
+// Organizacja nie może zostać bez właściciela.
+pub async fn remove_member(
+    db: web::Data<DbConn>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let user_id = match current_user_id(&req) {
+        Some(id) => id,
+        None => return HttpResponse::Unauthorized().body("Invalid or missing token"),
+    };
+    let (org_id, member_id) = path.into_inner();
+    let actor = match find_membership(&db, org_id, user_id).await {
+        Ok(membership) => membership,
+        Err(response) => return response,
+    };
+    let member = match org_membership::find_accepted()
+        .filter(org_membership::Column::OrgId.eq(org_id))
+        .filter(org_membership::Column::UserId.eq(member_id))
+        .one(&**db)
+        .await
+    {
+        Ok(Some(member)) => member,
+        Ok(None) => return HttpResponse::NotFound().body("Member not found"),
+        Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+    };
+
+    if member_id != user_id {
+        if actor.role < OrgRole::Admin {
+            return HttpResponse::Forbidden().body("Only owners and admins can manage members");
+        }
+        if member.role == OrgRole::Owner && actor.role != OrgRole::Owner {
+            return HttpResponse::Forbidden().body("Only owners can remove owners");
+        }
+    }
+
+    if member.role == OrgRole::Owner {
+        match org_membership::find_accepted()
+            .filter(org_membership::Column::OrgId.eq(org_id))
+            .filter(org_membership::Column::Role.eq(OrgRole::Owner))
+            .count(&**db)
+            .await
+        {
+            Ok(owners) if owners <= 1 => {
+                return HttpResponse::Conflict().body("Organization must keep at least one owner")
+            }
+            Ok(_) => {}
+            Err(_) => return HttpResponse::InternalServerError().body("Database error"),
+        }
+    }
+
+    match member.delete(&**db).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().body("Database error"),
+    }
 }
@@ -0,0 +1,358 @@
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::web::{self, Bytes, BytesMut};
+use actix_web::{Error, HttpMessage, HttpResponse};
+use chrono::Utc;
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::ActiveValue::Set;
+use sha2::Sha256;
+
+use crate::config::{idempotency_max_body_bytes, idempotency_ttl};
+use crate::jwt::{claims_from_request, derived_secret};
+
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+// Odpowiedź odtworzona z zapisanej — klient widzi, że to powtórka
+const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+const MAX_KEY_LEN: usize = 255;
+// Nagłówki sesji nie trafiają do bazy ani do powtórzonej odpowiedzi
+const UNSTORED_HEADERS: [header::HeaderName; 2] = [header::SET_COOKIE, header::AUTHORIZATION];
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "idempotency_keys")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    // Czyj to klucz: "user:{id}" albo "anonymous"
+    pub scope: String,
+    pub key: String,
+    // HMAC-SHA256 (klucz z sekretu serwera) metody, ścieżki i treści pierwszego żądania
+    pub request_hash: String,
+    // Brak — pierwsze żądanie jeszcze trwa
+    pub status_code: Option<i32>,
+    pub response_headers: Option<Json>,
+    pub response_body: Option<Vec<u8>>,
+    pub created_at: DateTimeWithTimeZone,
+    pub expires_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Co zrobić z żądaniem z kluczem
+enum Attempt {
+    // Pierwsze użycie klucza — wykonujemy żądanie i zapamiętujemy odpowiedź
+    First,
+    Replay(Model),
+    // Ten sam klucz z inną treścią albo na inny endpoint
+    Mismatch,
+    InProgress,
+}
+
+fn scope(req: &ServiceRequest) -> String {
+    match claims_from_request(req.request()) {
+        Some(claims) => format!("user:{}", claims.sub),
+        None => String::from("anonymous"),
+    }
+}
+
+// Żądania z hasłem albo wydające token sesji nie są zapamiętywane: ani skrót hasła,
+// ani token nie mogą trafić do tabeli kluczy
+fn is_credential_endpoint(path: &str) -> bool {
+    matches!(path, "/login" | "/register" | "/logout" | "/user/restore")
+        || path
+            .strip_prefix("/orgs/")
+            .is_some_and(|rest| rest.ends_with("/switch"))
+}
+
+fn request_hash(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&derived_secret("idempotency-request"))
+        .expect("HMAC accepts keys of any length");
+    mac.update(req.method().as_str().as_bytes());
+    mac.update(b"\n");
+    mac.update(req.path().as_bytes());
+    mac.update(b"?");
+    mac.update(req.query_string().as_bytes());
+    mac.update(b"\n");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// Zarezerwuj klucz albo sprawdź, co już pod nim zapisano
+async fn begin(db: &DbConn, scope: &str, key: &str, hash: &str) -> Result<Attempt, DbErr> {
+    let now = Utc::now();
+
+    // Po upływie okna klucz można użyć od nowa
+    Entity::delete_many()
+        .filter(Column::Scope.eq(scope))
+        .filter(Column::Key.eq(key))
+        .filter(Column::ExpiresAt.lte(now))
+        .exec(db)
+        .await?;
+
+    let reserved = Entity::insert(ActiveModel {
+        scope: Set(scope.to_owned()),
+        key: Set(key.to_owned()),
+        request_hash: Set(hash.to_owned()),
+        status_code: Set(None),
+        response_headers: Set(None),
+        response_body: Set(None),
+        created_at: Set(now.into()),
+        expires_at: Set((now + idempotency_ttl()).into()),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([Column::Scope, Column::Key])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    if reserved > 0 {
+        return Ok(Attempt::First);
+    }
+
+    let existing = Entity::find()
+        .filter(Column::Scope.eq(scope))
+        .filter(Column::Key.eq(key))
+        .one(db)
+        .await?;
+    Ok(match existing {
+        Some(row) if row.request_hash != hash => Attempt::Mismatch,
+        Some(row) if row.status_code.is_some() => Attempt::Replay(row),
+        // Brak wiersza: pierwsze żądanie właśnie się nie powiodło — klient może ponowić
+        _ => Attempt::InProgress,
+    })
+}
+
+async fn complete(
+    db: &DbConn,
+    scope: &str,
+    key: &str,
+    res: &HttpResponse<Bytes>,
+) -> Result<(), DbErr> {
+    let headers: Vec<(String, String)> = res
+        .headers()
+        .iter()
+        .filter(|(name, _)| !UNSTORED_HEADERS.contains(name))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+        .collect();
+
+    Entity::update_many()
+        .col_expr(
+            Column::StatusCode,
+            Expr::value(i32::from(res.status().as_u16())),
+        )
+        .col_expr(
+            Column::ResponseHeaders,
+            Expr::value(serde_json::json!(headers)),
+        )
+        .col_expr(Column::ResponseBody, Expr::value(res.body().to_vec()))
+        .filter(Column::Scope.eq(scope))
+        .filter(Column::Key.eq(key))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+// Zwolnij klucz, gdy pierwsze żądanie się nie powiodło — ponowienie wykona je od nowa
+async fn release(db: &DbConn, scope: &str, key: &str) {
+    if let Err(e) = Entity::delete_many()
+        .filter(Column::Scope.eq(scope))
+        .filter(Column::Key.eq(key))
+        .filter(Column::StatusCode.is_null())
+        .exec(db)
+        .await
+    {
+        eprintln!("Failed to release idempotency key: {}", e);
+    }
+}
+
+fn replay(stored: Model) -> HttpResponse {
+    let status = stored
+        .status_code
+        .and_then(|code| StatusCode::from_u16(code as u16).ok())
+        .unwrap_or(StatusCode::OK);
+    let headers: Vec<(String, String)> = stored
+        .response_headers
+        .and_then(|headers| serde_json::from_value(headers).ok())
+        .unwrap_or_default();
+
+    let mut builder = HttpResponse::build(status);
+    for header in headers {
+        builder.append_header(header);
+    }
+    builder
+        .insert_header((REPLAYED_HEADER, "true"))
+        .body(stored.response_body.unwrap_or_default())
+}
+
+// Wygasłe klucze usuwa zadanie w tle
+pub async fn purge_expired(db: &DbConn) -> Result<u64, DbErr> {
+    let res = Entity::delete_many()
+        .filter(Column::ExpiresAt.lte(Utc::now()))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
+// === Idempotency Middleware ===
+
+// Żądania POST z nagłówkiem Idempotency-Key: pierwsza odpowiedź jest zapamiętywana
+// i odtwarzana przy ponowieniu tego samego żądania z tym samym kluczem. Logowanie
+// i inne endpointy z hasłem lub tokenem sesji ignorują klucz (patrz `is_credential_endpoint`).
+#[derive(Clone)]
+pub struct IdempotencyMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for IdempotencyMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = IdempotencyMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IdempotencyMiddlewareService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct IdempotencyMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let key = match req.headers().get(IDEMPOTENCY_HEADER) {
+            Some(key) if req.method() == Method::POST && !is_credential_endpoint(req.path()) => {
+                key.to_str().ok().map(str::to_owned)
+            }
+            _ => {
+                let fut = self.service.call(req);
+                return Box::pin(async move { Ok(fut.await?.map_into_boxed_body()) });
+            }
+        };
+        let key = match key.filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LEN) {
+            Some(key) => key,
+            None => {
+                let res = HttpResponse::BadRequest().body("Invalid Idempotency-Key header");
+                return Box::pin(async move { Ok(req.into_response(res)) });
+            }
+        };
+        let db = match req.app_data::<web::Data<DbConn>>() {
+            Some(db) => db.get_ref().clone(),
+            None => {
+                let fut = self.service.call(req);
+                return Box::pin(async move { Ok(fut.await?.map_into_boxed_body()) });
+            }
+        };
+        let service = Rc::clone(&self.service);
+        let max_body = idempotency_max_body_bytes();
+        let too_large = move || {
+            HttpResponse::PayloadTooLarge().body(format!(
+                "Requests with Idempotency-Key can have at most {} bytes of body",
+                max_body
+            ))
+        };
+        let declared = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok()?.parse::<usize>().ok());
+        if declared.is_some_and(|len| len > max_body) {
+            let res = too_large();
+            return Box::pin(async move { Ok(req.into_response(res)) });
+        }
+
+        Box::pin(async move {
+            // Treść czytamy tutaj, żeby policzyć skrót żądania, i oddajemy ją handlerowi.
+            // Content-Length może kłamać (albo go brak), więc limit pilnujemy też w trakcie.
+            let mut payload = req.take_payload();
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                if body.len() + chunk.len() > max_body {
+                    return Ok(req.into_response(too_large()));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            let body = body.freeze();
+            let hash = request_hash(&req, &body);
+            req.set_payload(Payload::from(body));
+
+            let scope = scope(&req);
+            let rejected = match begin(&db, &scope, &key, &hash).await {
+                Ok(Attempt::First) => None,
+                Ok(Attempt::Replay(stored)) => Some(replay(stored)),
+                Ok(Attempt::Mismatch) => Some(
+                    HttpResponse::UnprocessableEntity()
+                        .body("Idempotency-Key was already used for a different request"),
+                ),
+                Ok(Attempt::InProgress) => Some(
+                    HttpResponse::Conflict()
+                        .body("A request with this Idempotency-Key is still being processed"),
+                ),
+                Err(_) => Some(HttpResponse::InternalServerError().body("Database error")),
+            };
+            if let Some(res) = rejected {
+                return Ok(req.into_response(res));
+            }
+
+            let res = match service.call(req).await {
+                Ok(res) => res,
+                Err(e) => {
+                    release(&db, &scope, &key).await;
+                    return Err(e);
+                }
+            };
+
+            let (req, res) = res.into_parts();
+            let (res, body) = res.into_parts();
+            let body = match body::to_bytes(body).await {
+                Ok(body) => body,
+                Err(_) => {
+                    release(&db, &scope, &key).await;
+                    let res = HttpResponse::InternalServerError().finish();
+                    return Ok(ServiceResponse::new(req, res));
+                }
+            };
+            let res = res.set_body(body);
+
+            // Błędów serwera nie zapamiętujemy — ponowienie ma szansę się udać
+            if res.status().is_server_error() {
+                release(&db, &scope, &key).await;
+            } else if let Err(e) = complete(&db, &scope, &key, &res).await {
+                eprintln!("Failed to store idempotent response: {}", e);
+            }
+            Ok(ServiceResponse::new(req, res.map_into_boxed_body()))
+        })
+    }
+}
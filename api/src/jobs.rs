@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt;
+use chrono::Utc;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter,
+    QuerySelect, RelationTrait,
+};
+
+use crate::config::delete_grace_period;
+use crate::storage::Storage;
+use crate::version::{self, update_versioned};
+use crate::{
+    attachment, attachment_variant, export, idempotency, post, quota, search_index, thumbnail, user,
+};
+
+// Jak często sprawdzamy, czy są konta/posty do trwałego usunięcia
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Jak często publikujemy szkice, których termin minął
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+
+// Jak często szukamy nowych obrazków do przetworzenia
+const THUMBNAIL_INTERVAL: Duration = Duration::from_secs(15);
+
+// Zadanie w tle: po okresie karencji usuwa na stałe miękko usunięte wiersze
+// oraz sprząta wygasłe eksporty danych, klucze idempotencji i stare liczniki żądań
+pub fn spawn_purge_job(db: DatabaseConnection, storage: Arc<dyn Storage>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = purge_deleted(&db, storage.as_ref()).await {
+                eprintln!("Purge job failed: {}", e);
+            }
+            if let Err(e) = export::purge_expired(&db).await {
+                eprintln!("Export cleanup failed: {}", e);
+            }
+            if let Err(e) = idempotency::purge_expired(&db).await {
+                eprintln!("Idempotency key cleanup failed: {}", e);
+            }
+            if let Err(e) = quota::purge_request_counts(&db).await {
+                eprintln!("Request count cleanup failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn purge_deleted(db: &DatabaseConnection, storage: &dyn Storage) -> Result<(), DbErr> {
+    let cutoff = Utc::now() - delete_grace_period();
+
+    // Kaskada usunie wiersze załączników, ale nie pliki — te sprzątamy najpierw
+    let files = attachment::Entity::find()
+        .inner_join(post::Entity)
+        .join(JoinType::InnerJoin, post::Relation::User.def())
+        .filter(
+            Condition::any()
+                .add(post::Column::DeletedAt.lt(cutoff))
+                .add(user::Column::DeletedAt.lt(cutoff)),
+        )
+        .all(db)
+        .await?;
+    let variants = attachment_variant::Entity::find()
+        .filter(attachment_variant::Column::AttachmentId.is_in(files.iter().map(|f| f.id)))
+        .all(db)
+        .await?;
+    let storage_keys = files
+        .into_iter()
+        .map(|f| f.storage_key)
+        .chain(variants.into_iter().map(|v| v.storage_key));
+    for storage_key in storage_keys {
+        if let Err(e) = storage.delete(&storage_key).await {
+            eprintln!("Failed to remove attachment file {}: {}", storage_key, e);
+        }
+    }
+
+    // Posty usuniętych użytkowników znikną przez kaskadę fk_posts_user
+    let user_ids: Vec<i32> = user::Entity::find()
+        .select_only()
+        .column(user::Column::Id)
+        .filter(user::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(db)
+        .await?;
+    let users = user::Entity::delete_many()
+        .filter(user::Column::Id.is_in(user_ids.clone()))
+        .exec(db)
+        .await?;
+
+    let post_ids: Vec<i32> = post::Entity::find()
+        .select_only()
+        .column(post::Column::Id)
+        .filter(post::Column::DeletedAt.lt(cutoff))
+        .into_tuple()
+        .all(db)
+        .await?;
+    let posts = post::Entity::delete_many()
+        .filter(post::Column::Id.is_in(post_ids.clone()))
+        .exec(db)
+        .await?;
+
+    // Usunięte posty wypadły z indeksu już przy miękkim usunięciu — tu sprzątamy resztki
+    search_index::remove_user_posts(user_ids).await;
+    search_index::sync_post_ids(db, post_ids).await;
+
+    if users.rows_affected > 0 || posts.rows_affected > 0 {
+        println!(
+            "Purged {} users and {} posts past the restore window",
+            users.rows_affected, posts.rows_affected
+        );
+    }
+    Ok(())
+}
+
+// Zadanie w tle: publikuje szkice, których `publish_at` już minął
+pub fn spawn_publish_job(db: DatabaseConnection) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(PUBLISH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = publish_due(&db).await {
+                eprintln!("Publish job failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn publish_due(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let due = post::find_active()
+        .filter(post::Column::Draft.eq(true))
+        .filter(post::Column::PublishAt.lte(Utc::now()))
+        .all(db)
+        .await?;
+
+    // Zapis przez model (a nie update_many): rośnie wersja, a indeks wyszukiwania
+    // dowiaduje się o publikacji. Szkic zmieniony w międzyczasie poczeka do następnego razu.
+    let mut published = 0;
+    for draft in due {
+        let mut active: post::ActiveModel = draft.into();
+        active.draft = Set(false);
+        match update_versioned(active, db, post::Column::Version).await {
+            Ok(saved) => {
+                search_index::sync_posts(vec![saved]).await;
+                published += 1;
+            }
+            Err(e) if version::is_conflict(&e) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    if published > 0 {
+        println!("Published {} scheduled drafts", published);
+    }
+    Ok(())
+}
+
+// Zadanie w tle: miniatury i usuwanie EXIF dla wgranych obrazków
+pub fn spawn_thumbnail_job(db: DatabaseConnection, storage: Arc<dyn Storage>) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(THUMBNAIL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = thumbnail::process_pending(&db, storage.as_ref()).await {
+                eprintln!("Thumbnail job failed: {}", e);
+            }
+        }
+    });
+}
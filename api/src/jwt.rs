@@ -1,50 +1,75 @@
 use std::env;
 
 use actix_service::{Service, Transform};
+use actix_web::cookie::{time, Cookie, SameSite};
 use actix_web::dev::ServiceRequest;
-use actix_web::{dev::ServiceResponse, Error, HttpResponse};
+use actix_web::{dev::ServiceResponse, Error, HttpRequest, HttpResponse};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::{Duration, Utc};
 use futures_util::future::Ready;
 use futures_util::future::{ok, LocalBoxFuture};
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::{
     rc::Rc,
     task::{Context, Poll},
 };
 
+use crate::config::env_or;
+
+// Nazwy ciasteczek i nagłówka używanych w trybie cookie
+pub const AUTH_COOKIE: &str = "auth_token";
+pub const CSRF_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+// Czas życia tokena (i ciasteczka z tokenem)
+const TOKEN_TTL_MINUTES: i64 = 60;
+
 pub fn hash_password(password: &str) -> String {
-    let hash = hash(password, DEFAULT_COST).unwrap();
-    hash
+    hash(password, DEFAULT_COST).unwrap()
 }
 pub fn vaildate_hash(password: &str, hash: &str) -> bool {
-    let validate = verify(password, hash).unwrap_or(false);
-    validate
+    verify(password, hash).unwrap_or(false)
 }
 // JWT klucz (do testów — w produkcji bezpiecznie trzymaj!)
-fn get_jwt_secret() -> Vec<u8> {
+pub fn get_jwt_secret() -> Vec<u8> {
     env::var("JWT_SECRET")
         .expect("JWT_SECRET not set")
         .into_bytes()
 }
 
+// Osobny klucz do innego celu niż JWT, wyprowadzony z sekretu z etykietą celu —
+// podpis z jednego miejsca nie przejdzie w drugim
+pub fn derived_secret(label: &str) -> Vec<u8> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&get_jwt_secret()).expect("HMAC accepts keys of any length");
+    mac.update(label.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // Aktywna organizacja; zmiana organizacji to nowy token
+    pub org: i32,
 }
 
 // Wygeneruj token
-pub fn generate_jwt(username: &str) -> String {
+pub fn generate_jwt(username: &str, org_id: i32) -> String {
     let expiration = Utc::now()
-        .checked_add_signed(Duration::minutes(60))
+        .checked_add_signed(Duration::minutes(TOKEN_TTL_MINUTES))
         .expect("valid timestamp")
         .timestamp();
 
     let claims = Claims {
         sub: username.to_owned(),
         exp: expiration as usize,
+        org: org_id,
     };
 
     encode(
@@ -55,6 +80,116 @@ pub fn generate_jwt(username: &str) -> String {
     .unwrap()
 }
 
+pub fn decode_jwt(token: &str) -> Option<Claims> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&get_jwt_secret()),
+        &Validation::default(),
+    )
+    .ok()
+    .map(|data| data.claims)
+}
+
+// Skąd pochodzi token — od tego zależy, czy wymagamy tokena CSRF
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Bearer,
+    Cookie,
+}
+
+// Token z nagłówka Authorization (pierwszeństwo) albo z ciasteczka HttpOnly
+pub fn request_token(req: &HttpRequest) -> Option<(String, TokenSource)> {
+    if let Some(auth_header) = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+    {
+        return auth_header
+            .strip_prefix("Bearer ")
+            .map(|token| (token.to_owned(), TokenSource::Bearer));
+    }
+
+    req.cookie(AUTH_COOKIE)
+        .map(|cookie| (cookie.value().to_owned(), TokenSource::Cookie))
+}
+
+// Zdekodowane claims zalogowanego użytkownika (Bearer albo cookie)
+pub fn claims_from_request(req: &HttpRequest) -> Option<Claims> {
+    request_token(req).and_then(|(token, _)| decode_jwt(&token))
+}
+
+// Losowy, nieprzewidywalny token (CSRF, linki do pobrania itp.)
+pub fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+pub fn generate_csrf_token() -> String {
+    random_token(32)
+}
+
+fn same_site() -> SameSite {
+    match env_or("COOKIE_SAMESITE", String::from("Strict"))
+        .to_lowercase()
+        .as_str()
+    {
+        "lax" => SameSite::Lax,
+        "none" => SameSite::None,
+        _ => SameSite::Strict,
+    }
+}
+
+// Ciasteczko z tokenem (HttpOnly) i ciasteczko CSRF (czytelne dla JS, do double-submit)
+pub fn auth_cookies(token: &str, csrf_token: &str) -> (Cookie<'static>, Cookie<'static>) {
+    let max_age = time::Duration::minutes(TOKEN_TTL_MINUTES);
+
+    let auth = Cookie::build(AUTH_COOKIE, token.to_owned())
+        .path("/")
+        .secure(true)
+        .http_only(true)
+        .same_site(same_site())
+        .max_age(max_age)
+        .finish();
+
+    let csrf = Cookie::build(CSRF_COOKIE, csrf_token.to_owned())
+        .path("/")
+        .secure(true)
+        .http_only(false)
+        .same_site(same_site())
+        .max_age(max_age)
+        .finish();
+
+    (auth, csrf)
+}
+
+// Ciasteczka do wylogowania (puste i natychmiast wygasające)
+pub fn expired_auth_cookies() -> (Cookie<'static>, Cookie<'static>) {
+    let (mut auth, mut csrf) = auth_cookies("", "");
+    auth.make_removal();
+    csrf.make_removal();
+    (auth, csrf)
+}
+
+// Double-submit: wartość nagłówka X-CSRF-Token musi być równa ciasteczku csrf_token
+fn csrf_valid(req: &HttpRequest) -> bool {
+    let cookie = match req.cookie(CSRF_COOKIE) {
+        Some(c) => c,
+        None => return false,
+    };
+    let header = match req.headers().get(CSRF_HEADER).and_then(|h| h.to_str().ok()) {
+        Some(h) => h,
+        None => return false,
+    };
+
+    let (a, b) = (cookie.value().as_bytes(), header.as_bytes());
+    !a.is_empty()
+        && a.len() == b.len()
+        && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 // === JWT Middleware ===
 
 #[derive(Clone)]
@@ -97,31 +232,23 @@ where
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let auth_header = req
-            .headers()
-            .get("Authorization")
-            .and_then(|h| h.to_str().ok());
-
-        if let Some(auth_header) = auth_header {
-            if let Some(token) = auth_header.strip_prefix("Bearer ") {
-                let validation = Validation::default();
-                let result = decode::<Claims>(
-                    token,
-                    &DecodingKey::from_secret(&get_jwt_secret()),
-                    &validation,
-                );
-
-                if result.is_ok() {
-                    let fut = self.service.call(req);
-                    return Box::pin(async move { fut.await });
+        let response = match request_token(req.request()) {
+            Some((token, source)) if decode_jwt(&token).is_some() => {
+                // W trybie cookie żądania zmieniające stan muszą mieć token CSRF
+                if source == TokenSource::Cookie
+                    && !req.method().is_safe()
+                    && !csrf_valid(req.request())
+                {
+                    HttpResponse::Forbidden().body("Invalid or missing CSRF token")
+                } else {
+                    return Box::pin(self.service.call(req));
                 }
             }
-        }
+            _ => HttpResponse::Unauthorized().body("Invalid or missing token"),
+        };
 
         let (req, _payload) = req.into_parts();
-        let response = HttpResponse::Unauthorized()
-            .body("Invalid or missing token")
-            .map_into_boxed_body();
+        let response = response.map_into_boxed_body();
         let res = ServiceResponse::new(req, response);
         Box::pin(async { Ok(res) })
     }
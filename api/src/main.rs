@@ -1,13 +1,39 @@
 use actix_web::{middleware::Logger, web, App, HttpServer};
+use idempotency::IdempotencyMiddleware;
 use jwt::JwtMiddleware;
 use migration::{Migrator, MigratorTrait};
+use quota::RequestQuotaMiddleware;
 use sea_orm::{Database, DatabaseConnection};
+use search::SearchBackend;
 use std::env;
 
+mod attachment;
+mod attachment_variant;
+mod audit;
+mod comment;
+mod config;
+mod export;
 mod handle;
+mod idempotency;
+mod jobs;
 mod jwt;
+mod markdown;
+mod org_membership;
+mod organization;
 mod post;
+mod post_revision;
+mod post_share;
+mod post_tag;
+mod quota;
+mod reaction;
+mod rrule;
+mod search;
+mod search_index;
+mod storage;
+mod tag;
+mod thumbnail;
 mod user; // Ensure this module is included
+mod version;
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::from_path("../.env").ok();
@@ -26,31 +52,179 @@ async fn main() -> std::io::Result<()> {
         Ok(_) => println!("Database migrations ran successfully."),
         Err(e) => {
             eprintln!("Failed to apply 'up' migration: {}", e);
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Migration up failed",
-            ));
+            return Err(std::io::Error::other("Migration up failed"));
         }
     }
+    // Embedded search index instead of PostgreSQL full-text search
+    if config::search_backend() == SearchBackend::Tantivy {
+        let index = search_index::init(&config::search_index_dir())
+            .map_err(|e| std::io::Error::other(format!("Failed to open search index: {}", e)))?;
+
+        // `rebuild-search-index` rebuilds the index from the database and exits;
+        // an empty index (first start) is filled the same way
+        let rebuild = env::args().nth(1).as_deref() == Some("rebuild-search-index");
+        if rebuild || index.num_docs() == 0 {
+            match index.rebuild(&db).await {
+                Ok(count) => println!("Search index rebuilt with {} posts.", count),
+                Err(e) => {
+                    eprintln!("Failed to rebuild search index: {}", e);
+                    return Err(std::io::Error::other("Search index rebuild failed"));
+                }
+            }
+        }
+        if rebuild {
+            return Ok(());
+        }
+    }
+
+    // Attachment storage: local directory or an S3-compatible bucket
+    let storage = storage::from_config()
+        .map_err(|e| std::io::Error::other(format!("Failed to set up storage: {}", e)))?;
+
+    // Background job that hard-deletes accounts past the restore window
+    // (with their attachment files) and removes expired data exports
+    jobs::spawn_purge_job(db.clone(), storage.clone());
+    jobs::spawn_publish_job(db.clone());
+    // Background job that generates thumbnails and strips EXIF from uploaded images
+    jobs::spawn_thumbnail_job(db.clone(), storage.clone());
+
     // Start the Actix Web server
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(db.clone())) // Share database connection with the app
+            .app_data(web::Data::from(storage.clone()))
             .service(web::resource("/all").route(web::get().to(handle::get_users)))
             .service(web::resource("/login").route(web::post().to(handle::login)))
             .service(web::resource("/register").route(web::post().to(handle::register)))
+            .service(web::resource("/logout").route(web::post().to(handle::logout)))
+            .service(web::resource("/users/{id}").route(web::get().to(handle::public_profile)))
+            .service(web::resource("/posts/{id}").route(web::get().to(handle::get_post)))
+            .service(web::resource("/export/{token}").route(web::get().to(handle::download_export)))
+            .service(
+                web::resource("/attachments/{id}/download")
+                    .route(web::get().to(handle::download_attachment)),
+            )
+            // Replays stored responses to retried POSTs carrying an Idempotency-Key
+            .wrap(IdempotencyMiddleware)
+            // Outside idempotency: a 429 must not be stored as the key's response
+            .wrap(RequestQuotaMiddleware)
             .wrap(Logger::new("%a %r %s %b %D %U %{User-Agent}i"))
+            // Registered before the `/user` scope: a deleted account has no valid token
+            .service(web::resource("/user/restore").route(web::post().to(handle::restore)))
             .service(
                 web::scope("/user")
                     .wrap(JwtMiddleware)
                     .route("/settings", web::get().to(handle::settings))
                     .route("/update", web::put().to(handle::update))
-                    .route("/delete", web::delete().to(handle::delete)),
+                    .route("/delete", web::delete().to(handle::delete))
+                    .route("/export", web::post().to(handle::request_export))
+                    .route("/export/{id}", web::get().to(handle::export_status))
+                    .route("/usage", web::get().to(handle::usage)),
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(JwtMiddleware)
+                    .route("/audit", web::get().to(handle::audit_events))
+                    .route("/users/{id}/quota", web::put().to(handle::set_quota)),
+            )
+            .service(
+                web::scope("/orgs")
+                    .wrap(JwtMiddleware)
+                    .route("", web::get().to(handle::list_orgs))
+                    .route("", web::post().to(handle::create_org))
+                    .route("/invitations", web::get().to(handle::org_invitations))
+                    .route(
+                        "/invitations/{id}/accept",
+                        web::post().to(handle::accept_org_invitation),
+                    )
+                    .route(
+                        "/invitations/{id}/decline",
+                        web::post().to(handle::decline_org_invitation),
+                    )
+                    .route("/{id}/switch", web::post().to(handle::switch_org))
+                    .route("/{id}/public", web::put().to(handle::set_org_public))
+                    .route("/{id}/members", web::get().to(handle::list_members))
+                    .route("/{id}/members", web::post().to(handle::add_member))
+                    .route(
+                        "/{id}/members/{user_id}",
+                        web::delete().to(handle::remove_member),
+                    ),
+            )
+            .service(
+                web::scope("/search")
+                    .wrap(JwtMiddleware)
+                    .route("", web::get().to(handle::search)),
             )
             .service(
                 web::scope("/todos")
                     .wrap(JwtMiddleware)
-                    .route("/add", web::post().to(handle::add_post)),
+                    .route("", web::get().to(handle::list_todos))
+                    .route("/add", web::post().to(handle::add_post))
+                    .route("/tags", web::get().to(handle::tag_suggestions))
+                    .route("/reacted", web::get().to(handle::reacted_todos))
+                    .route("/shared", web::get().to(handle::shared_with_me))
+                    .route("/invitations", web::get().to(handle::share_invitations))
+                    .route(
+                        "/invitations/{id}/accept",
+                        web::post().to(handle::accept_invitation),
+                    )
+                    .route(
+                        "/invitations/{id}/decline",
+                        web::post().to(handle::decline_invitation),
+                    )
+                    .route("/overdue", web::get().to(handle::overdue_todos))
+                    .route("/due-soon", web::get().to(handle::due_soon_todos))
+                    .route("/drafts", web::get().to(handle::list_drafts))
+                    .route("/trash", web::get().to(handle::list_trash))
+                    .route("/{id}", web::put().to(handle::update_todo))
+                    .route("/{id}", web::delete().to(handle::delete_todo))
+                    .route("/{id}/restore", web::post().to(handle::restore_todo))
+                    .route("/{id}/revisions", web::get().to(handle::list_revisions))
+                    .route("/{id}/revisions/diff", web::get().to(handle::revision_diff))
+                    .route(
+                        "/{id}/revisions/{revision}/restore",
+                        web::post().to(handle::restore_revision),
+                    )
+                    .route("/{id}/complete", web::post().to(handle::complete_todo))
+                    .route("/{id}/reopen", web::post().to(handle::reopen_todo))
+                    .route("/{id}/occurrences", web::get().to(handle::todo_occurrences))
+                    .route("/{id}/skip", web::post().to(handle::skip_occurrence))
+                    .route("/{id}/end-series", web::post().to(handle::end_series))
+                    .route("/{id}/tree", web::get().to(handle::todo_tree))
+                    .route("/{id}/move", web::post().to(handle::move_todo))
+                    .route("/{id}/visibility", web::put().to(handle::set_visibility))
+                    .route("/{id}/schedule", web::put().to(handle::schedule_draft))
+                    .route("/{id}/schedule", web::delete().to(handle::unschedule_draft))
+                    .route("/{id}/publish", web::post().to(handle::publish_draft))
+                    .route("/{id}/shares", web::get().to(handle::list_shares))
+                    .route("/{id}/shares", web::post().to(handle::share_post))
+                    .route(
+                        "/{id}/shares/{user_id}",
+                        web::delete().to(handle::revoke_share),
+                    )
+                    .route(
+                        "/{id}/reactions/{kind}",
+                        web::post().to(handle::toggle_reaction),
+                    )
+                    .route("/{id}/attachments", web::get().to(handle::list_attachments))
+                    .route(
+                        "/{id}/attachments",
+                        web::post().to(handle::upload_attachments),
+                    )
+                    .route(
+                        "/{id}/attachments/{attachment_id}",
+                        web::delete().to(handle::delete_attachment),
+                    )
+                    .route("/{id}/comments", web::get().to(handle::list_comments))
+                    .route("/{id}/comments", web::post().to(handle::add_comment))
+                    .route(
+                        "/{id}/comments/{comment_id}",
+                        web::put().to(handle::update_comment),
+                    )
+                    .route(
+                        "/{id}/comments/{comment_id}",
+                        web::delete().to(handle::delete_comment),
+                    ),
             )
     })
     .bind(("127.0.0.1", 8000))? // Bind to localhost on port 8080
@@ -0,0 +1,30 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use ammonia::Builder;
+use pulldown_cmark::{html, Options, Parser};
+
+// Rozszerzenia Markdowna, które renderujemy (poza CommonMark)
+const MARKDOWN_OPTIONS: Options = Options::ENABLE_TABLES
+    .union(Options::ENABLE_STRIKETHROUGH)
+    .union(Options::ENABLE_TASKLISTS);
+
+// Sanityzacja wyniku: domyślna lista bezpiecznych tagów i atrybutów ammonia (bez skryptów,
+// stylów i handlerów `on*`), linki tylko http(s)/mailto i zawsze z `rel="nofollow"`
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("noopener noreferrer nofollow"))
+        // Checkboxy list zadań z ENABLE_TASKLISTS
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"]);
+    builder
+});
+
+// Markdown użytkownika jako bezpieczny HTML
+pub fn render(source: &str) -> String {
+    let mut unsafe_html = String::with_capacity(source.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, MARKDOWN_OPTIONS));
+    SANITIZER.clean(&unsafe_html).to_string()
+}
@@ -0,0 +1,111 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Query;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    // Pracuje na postach organizacji
+    #[sea_orm(string_value = "member")]
+    Member,
+    // Może też dodawać i usuwać członków
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    // Może też nadawać i odbierać rolę właściciela
+    #[sea_orm(string_value = "owner")]
+    Owner,
+}
+
+// `/orgs/{id}/members` — zaproszenie istniejącego użytkownika po adresie e-mail
+#[derive(Deserialize)]
+pub struct MemberAdd {
+    pub email: String,
+    pub role: OrgRole,
+}
+
+// Członek na liście członków organizacji
+#[derive(Serialize)]
+pub struct OrgMember {
+    pub user_id: i32,
+    pub name: String,
+    pub lastname: String,
+    pub role: OrgRole,
+    pub joined_at: DateTimeWithTimeZone,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "org_memberships")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub org_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: i32,
+    pub role: OrgRole,
+    pub created_at: DateTimeWithTimeZone,
+    // Brak daty — zaproszenie czeka na akceptację i nie daje jeszcze dostępu
+    pub accepted_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::organization::Entity",
+        from = "Column::OrgId",
+        to = "super::organization::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Organization,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Przyjęte członkostwa
+pub fn find_accepted() -> Select<Entity> {
+    Entity::find().filter(Column::AcceptedAt.is_not_null())
+}
+
+// Aktywni użytkownicy należący do organizacji
+pub fn find_members(org_id: i32) -> Select<super::user::Entity> {
+    super::user::find_active().filter(
+        super::user::Column::Id.in_subquery(
+            Query::select()
+                .column(Column::UserId)
+                .from(Entity)
+                .and_where(Column::OrgId.eq(org_id))
+                .and_where(Column::AcceptedAt.is_not_null())
+                .to_owned(),
+        ),
+    )
+}
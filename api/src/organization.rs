@@ -0,0 +1,140 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Query, SelectStatement};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::org_membership::{self, OrgRole};
+
+// Zalogowany użytkownik w aktywnej organizacji (z tokena).
+// Każde zapytanie o posty jest zawężone do `org_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tenant {
+    pub user_id: i32,
+    pub org_id: i32,
+}
+
+// `/orgs` — nowa organizacja; zakładający zostaje jej właścicielem
+#[derive(Deserialize)]
+pub struct OrgCreate {
+    pub name: String,
+    pub public: Option<bool>,
+}
+
+// `PUT /orgs/{id}/public` — czy dane organizacji są dostępne bez logowania (tylko właściciel)
+#[derive(Deserialize)]
+pub struct SetOrgPublic {
+    pub public: bool,
+}
+
+// Organizacja na liście organizacji użytkownika, z jego rolą
+#[derive(Serialize)]
+pub struct OrgSummary {
+    #[serde(flatten)]
+    pub org: Model,
+    pub role: OrgRole,
+    // Czy to organizacja z bieżącego tokena
+    pub active: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "organizations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    // Niezalogowani widzą nieprywatne posty i profile tylko z organizacji publicznych
+    pub public: bool,
+    pub created_by: i32,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::org_membership::Entity")]
+    OrgMembership,
+    #[sea_orm(has_many = "super::post::Entity")]
+    Post,
+}
+
+impl Related<super::org_membership::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrgMembership.def()
+    }
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Nazwa prywatnej przestrzeni zakładanej przy rejestracji (jak w migracji istniejących kont)
+pub fn personal_name(user_name: &str) -> String {
+    format!("{}'s workspace", user_name)
+}
+
+// Utwórz organizację razem z członkostwem właściciela
+pub async fn create_with_owner(
+    db: &DbConn,
+    name: &str,
+    public: bool,
+    owner_id: i32,
+) -> Result<Model, DbErr> {
+    let txn = db.begin().await?;
+
+    let org = ActiveModel {
+        name: Set(name.to_owned()),
+        public: Set(public),
+        created_by: Set(owner_id),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    org_membership::ActiveModel {
+        org_id: Set(org.id),
+        user_id: Set(owner_id),
+        role: Set(OrgRole::Owner),
+        created_at: Set(chrono::Utc::now().into()),
+        accepted_at: Set(Some(chrono::Utc::now().into())),
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+    Ok(org)
+}
+
+// Organizacja, w której użytkownik zaczyna po zalogowaniu: ta, do której dołączył najwcześniej
+pub async fn default_for(db: &DbConn, user_id: i32) -> Result<Option<i32>, DbErr> {
+    Ok(org_membership::find_accepted()
+        .filter(org_membership::Column::UserId.eq(user_id))
+        .order_by_asc(org_membership::Column::CreatedAt)
+        .order_by_asc(org_membership::Column::OrgId)
+        .one(db)
+        .await?
+        .map(|membership| membership.org_id))
+}
+
+// Id organizacji publicznych — do zawężania zapytań niezalogowanych
+pub fn public_ids() -> SelectStatement {
+    Query::select()
+        .column(Column::Id)
+        .from(Entity)
+        .and_where(Column::Public.eq(true))
+        .to_owned()
+}
+
+// Członkowie organizacji publicznych
+pub fn public_members() -> SelectStatement {
+    Query::select()
+        .column(org_membership::Column::UserId)
+        .from(org_membership::Entity)
+        .and_where(org_membership::Column::OrgId.in_subquery(public_ids()))
+        .and_where(org_membership::Column::AcceptedAt.is_not_null())
+        .to_owned()
+}
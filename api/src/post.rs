@@ -1,10 +1,220 @@
 use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::{self, Set};
+use sea_orm::Condition;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::attachment::AttachmentResponse;
+use crate::reaction::ReactionKind;
+use crate::rrule::RRule;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[sea_orm(num_value = 0)]
+    Low,
+    #[sea_orm(num_value = 1)]
+    Normal,
+    #[sea_orm(num_value = 2)]
+    High,
+    #[sea_orm(num_value = 3)]
+    Urgent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    #[sea_orm(string_value = "open")]
+    Open,
+    #[sea_orm(string_value = "in_progress")]
+    InProgress,
+    #[sea_orm(string_value = "done")]
+    Done,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    // Tylko właściciel
+    #[default]
+    #[sea_orm(string_value = "private")]
+    Private,
+    // Każdy, kto zna id posta, ale bez list i wyszukiwania
+    #[sea_orm(string_value = "unlisted")]
+    Unlisted,
+    // Wszyscy, także w listach, profilu i wyszukiwaniu
+    #[sea_orm(string_value = "public")]
+    Public,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    // Zwykły tekst, pokazywany tak, jak został zapisany
+    #[default]
+    #[sea_orm(string_value = "plain")]
+    Plain,
+    // Markdown renderowany na serwerze do oczyszczonego HTML
+    #[sea_orm(string_value = "markdown")]
+    Markdown,
+}
+
+// `?content=raw|html` — domyślnie obie postacie treści
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentView {
+    #[default]
+    Both,
+    // Tylko `content`
+    Raw,
+    // Tylko `content_html`
+    Html,
+}
+
+// `/posts/{id}?content=html`
+#[derive(Deserialize)]
+pub struct ContentQuery {
+    #[serde(default)]
+    pub content: ContentView,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PostCreate {
     pub title: String,
     pub content: String,
+    #[serde(default)]
+    pub content_format: Option<ContentFormat>,
+    #[serde(default)]
+    pub due_at: Option<DateTimeWithTimeZone>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub status: Option<TodoStatus>,
+    // np. "FREQ=WEEKLY;BYDAY=MO,TH" — wymaga `due_at` (pierwsze wystąpienie)
+    #[serde(default)]
+    pub rrule: Option<String>,
+    // Podzadanie innego todo
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub visibility: Option<Visibility>,
+    // Szkic nie jest widoczny dla innych, dopóki nie zostanie opublikowany
+    #[serde(default)]
+    pub draft: Option<bool>,
+    // Automatyczna publikacja szkicu; termin w przyszłości sam czyni post szkicem
+    #[serde(default)]
+    pub publish_at: Option<DateTimeWithTimeZone>,
+}
+
+// `PUT /todos/{id}` — pominięte pola zostają bez zmian
+#[derive(Deserialize)]
+pub struct PostUpdate {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub content_format: Option<ContentFormat>,
+    #[serde(default)]
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub priority: Option<Priority>,
+}
+
+// `PUT /todos/{id}/schedule`
+#[derive(Deserialize)]
+pub struct SchedulePost {
+    pub publish_at: DateTimeWithTimeZone,
+}
+
+// `/todos/{id}/visibility`
+#[derive(Deserialize)]
+pub struct SetVisibility {
+    pub visibility: Visibility,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    // Post ma co najmniej jeden z tagów
+    #[default]
+    Any,
+    // Post ma wszystkie tagi
+    All,
+}
+
+// `/todos?tags=praca,dom&match=all`
+#[derive(Deserialize)]
+pub struct PostListQuery {
+    pub tags: Option<String>,
+    #[serde(rename = "match", default)]
+    pub tag_match: TagMatch,
+    #[serde(default)]
+    pub content: ContentView,
+}
+
+// Post w odpowiedziach API razem z danymi z powiązanych tabel
+#[derive(Serialize)]
+pub struct PostResponse {
+    #[serde(flatten)]
+    pub post: Model,
+    pub tags: Vec<String>,
+    pub comment_count: u64,
+    // Liczba reakcji każdego rodzaju, np. {"like": 3}
+    pub reactions: BTreeMap<ReactionKind, u64>,
+    pub attachments: Vec<AttachmentResponse>,
+}
+
+impl PostResponse {
+    // JSON posta tylko z wybraną postacią treści
+    pub fn with_content(self, view: ContentView) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            match view {
+                ContentView::Both => {}
+                ContentView::Raw => {
+                    fields.remove("content_html");
+                }
+                ContentView::Html => {
+                    fields.remove("content");
+                }
+            }
+        }
+        value
+    }
+}
+
+// `/todos/{id}/move` — `null` przenosi poddrzewo na najwyższy poziom
+#[derive(Deserialize)]
+pub struct MovePost {
+    pub parent_id: Option<i32>,
+}
+
+// Todo z całym poddrzewem i procentem ukończenia
+#[derive(Serialize)]
+pub struct TodoTree {
+    #[serde(flatten)]
+    pub todo: Model,
+    pub completion: f64,
+    pub children: Vec<TodoTree>,
+}
+
+// `/todos/due-soon?hours=24`
+#[derive(Deserialize)]
+pub struct DueSoonQuery {
+    pub hours: Option<i64>,
+}
+
+// `/todos/{id}/occurrences?count=5`
+#[derive(Deserialize)]
+pub struct OccurrencesQuery {
+    pub count: Option<usize>,
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq, DeriveEntityModel)]
@@ -14,7 +224,33 @@ pub struct Model {
     pub id: i32,
     pub title: String,
     pub content: String,
+    pub content_format: ContentFormat,
+    // Wyrenderowany Markdown (tylko dla `content_format = markdown`), odświeżany przy zapisie
+    pub content_html: Option<String>,
     pub user_id: i32,
+    // Organizacja, do której należy post — poza nią post nie istnieje
+    pub org_id: i32,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    pub created_by: Option<i32>,
+    pub updated_by: Option<i32>,
+    pub done: bool,
+    pub completed_at: Option<DateTimeWithTimeZone>,
+    pub due_at: Option<DateTimeWithTimeZone>,
+    pub priority: Priority,
+    pub status: TodoStatus,
+    pub rrule: Option<String>,
+    pub series_id: Option<i32>,
+    pub recurrence_start: Option<DateTimeWithTimeZone>,
+    pub parent_id: Option<i32>,
+    pub visibility: Visibility,
+    // Szkic widzi tylko autor (i osoby, którym go udostępnił)
+    pub draft: bool,
+    // Kiedy szkic opublikuje zadanie w tle; po publikacji — kiedy to nastąpiło
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    // Numer wersji wiersza (ETag), rośnie przy każdym zapisie
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -25,6 +261,25 @@ pub enum Relation {
         to = "super::user::Column::Id"
     )]
     User,
+    #[sea_orm(
+        belongs_to = "super::organization::Entity",
+        from = "Column::OrgId",
+        to = "super::organization::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Organization,
+    #[sea_orm(has_many = "super::post_tag::Entity")]
+    PostTag,
+    #[sea_orm(has_many = "super::comment::Entity")]
+    Comment,
+    #[sea_orm(has_many = "super::reaction::Entity")]
+    Reaction,
+    #[sea_orm(has_many = "super::post_share::Entity")]
+    PostShare,
+    #[sea_orm(has_many = "super::post_revision::Entity")]
+    PostRevision,
+    #[sea_orm(has_many = "super::attachment::Entity")]
+    Attachment,
 }
 
 impl Related<super::user::Entity> for Entity {
@@ -33,7 +288,197 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+impl Related<super::organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
+impl Related<super::comment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Comment.def()
+    }
+}
+
+impl Related<super::post_share::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostShare.def()
+    }
+}
+
+impl Related<super::post_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostRevision.def()
+    }
+}
+
+impl Related<super::attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
+impl Related<super::reaction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reaction.def()
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::post_tag::Relation::Tag.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::post_tag::Relation::Post.def().rev())
+    }
+}
+
+// Znaczniki czasu ustawiane automatycznie przy każdym zapisie
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        if insert {
+            self.created_at = Set(now);
+        } else if let ActiveValue::Unchanged(version) | ActiveValue::Set(version) = self.version {
+            self.version = Set(version + 1);
+        }
+        self.updated_at = Set(now);
+
+        // Treść albo format się zmieniły — odśwież wyrenderowany HTML
+        if self.content.is_set() || self.content_format.is_set() {
+            self.content_html = Set(
+                match (self.content.try_as_ref(), self.content_format.try_as_ref()) {
+                    (Some(content), Some(ContentFormat::Markdown)) => {
+                        Some(crate::markdown::render(content))
+                    }
+                    _ => None,
+                },
+            );
+        }
+        Ok(self)
+    }
+
+    // Każda nowa wersja tytułu i treści trafia do historii rewizji. Indeks wyszukiwania
+    // aktualizujemy dopiero po zatwierdzeniu transakcji — patrz `search_index::sync_posts`.
+    async fn after_save<C>(model: Model, db: &C, _insert: bool) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        crate::post_revision::record(db, &model).await?;
+        Ok(model)
+    }
+}
+
+impl ActiveModel {
+    // `done`, `status` i `completed_at` zawsze zmieniamy razem
+    pub fn set_status(&mut self, status: TodoStatus) {
+        let done = status == TodoStatus::Done;
+        self.done = Set(done);
+        self.completed_at = Set(done.then(|| chrono::Utc::now().into()));
+        self.status = Set(status);
+    }
+}
+
+impl Model {
+    // Czy `viewer` (None = niezalogowany) może otworzyć ten post bezpośrednio
+    pub fn is_visible_to(&self, viewer: Option<i32>) -> bool {
+        (self.visibility != Visibility::Private && !self.draft) || viewer == Some(self.user_id)
+    }
+
+    pub fn recurrence(&self) -> Option<RRule> {
+        self.rrule.as_deref().and_then(|rule| rule.parse().ok())
+    }
+
+    // Kolejne terminy serii po terminie tego wystąpienia
+    pub fn upcoming_occurrences(&self, limit: usize) -> Vec<DateTimeWithTimeZone> {
+        match (self.recurrence(), self.due_at) {
+            (Some(rule), Some(due)) => {
+                rule.occurrences_after(self.recurrence_start.unwrap_or(due), due, limit)
+            }
+            _ => vec![],
+        }
+    }
+
+    // Następne wystąpienie serii jako nowe, otwarte todo (None, gdy seria się skończyła)
+    pub fn next_occurrence(&self) -> Option<ActiveModel> {
+        let due_at = self.upcoming_occurrences(1).into_iter().next()?;
+
+        let mut next = ActiveModel {
+            title: Set(self.title.clone()),
+            content: Set(self.content.clone()),
+            content_format: Set(self.content_format),
+            user_id: Set(self.user_id),
+            org_id: Set(self.org_id),
+            created_by: Set(self.updated_by),
+            updated_by: Set(self.updated_by),
+            due_at: Set(Some(due_at)),
+            priority: Set(self.priority),
+            rrule: Set(self.rrule.clone()),
+            series_id: Set(Some(self.series_id.unwrap_or(self.id))),
+            recurrence_start: Set(self.recurrence_start),
+            parent_id: Set(self.parent_id),
+            visibility: Set(self.visibility),
+            ..Default::default()
+        };
+        next.set_status(TodoStatus::Open);
+        Some(next)
+    }
+}
+
+// Zbuduj drzewo od `root` z listy postów tego samego właściciela.
+// Ukończone todo ma 100%, liść nieukończony 0%, a pozostałe — średnią z dzieci.
+pub fn build_tree(root: Model, posts: Vec<Model>) -> TodoTree {
+    let mut children: HashMap<i32, Vec<Model>> = HashMap::new();
+    for post in posts {
+        if let Some(parent_id) = post.parent_id {
+            children.entry(parent_id).or_default().push(post);
+        }
+    }
+    build_node(root, &mut children)
+}
+
+fn build_node(todo: Model, children: &mut HashMap<i32, Vec<Model>>) -> TodoTree {
+    let nodes: Vec<TodoTree> = children
+        .remove(&todo.id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|child| build_node(child, children))
+        .collect();
 
+    let completion = if todo.done {
+        100.0
+    } else if nodes.is_empty() {
+        0.0
+    } else {
+        nodes.iter().map(|n| n.completion).sum::<f64>() / nodes.len() as f64
+    };
 
+    TodoTree {
+        todo,
+        completion,
+        children: nodes,
+    }
+}
+
+// Posty, które nie zostały miękko usunięte
+pub fn find_active() -> Select<Entity> {
+    Entity::find().filter(Column::DeletedAt.is_null())
+}
 
+// Posty, które `viewer` widzi na listach: opublikowane publiczne i własne
+pub fn listed_for(viewer: Option<i32>) -> Condition {
+    let condition = Condition::any().add(
+        Condition::all()
+            .add(Column::Visibility.eq(Visibility::Public))
+            .add(Column::Draft.eq(false)),
+    );
+    match viewer {
+        Some(viewer) => condition.add(Column::UserId.eq(viewer)),
+        None => condition,
+    }
+}
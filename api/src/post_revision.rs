@@ -0,0 +1,364 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::Set;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+
+use crate::post::ContentFormat;
+
+// Powyżej tylu porównań linii (n * m) nie liczymy LCS — pokazujemy zamianę całości
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+// `/todos/{id}/revisions/diff?from=1&to=3`
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+// Jedna linia różnicy między dwiema wersjami tekstu
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+// Różnica tytułu i treści między dwiema rewizjami
+#[derive(Serialize)]
+pub struct RevisionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub title: Vec<DiffLine>,
+    pub content: Vec<DiffLine>,
+}
+
+// Rewizja w historii razem ze zmianami względem poprzedniej (pierwsza — względem pustego posta)
+#[derive(Serialize)]
+pub struct RevisionEntry {
+    #[serde(flatten)]
+    pub revision: Model,
+    pub changes: RevisionDiff,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "post_revisions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub post_id: i32,
+    pub revision: i32,
+    pub title: String,
+    pub content: String,
+    pub content_format: ContentFormat,
+    pub edited_by: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::post::Entity",
+        from = "Column::PostId",
+        to = "super::post::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Post,
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Zapisz tytuł, treść i jej format jako nową rewizję, jeśli różnią się od ostatniej.
+// Zmiany stanu, terminu itp. nie tworzą rewizji.
+pub async fn record<C>(db: &C, post: &super::post::Model) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let latest = Entity::find()
+        .filter(Column::PostId.eq(post.id))
+        .order_by_desc(Column::Revision)
+        .one(db)
+        .await?;
+    if latest.as_ref().is_some_and(|r| {
+        r.title == post.title
+            && r.content == post.content
+            && r.content_format == post.content_format
+    }) {
+        return Ok(());
+    }
+
+    ActiveModel {
+        post_id: Set(post.id),
+        revision: Set(latest.map_or(1, |r| r.revision + 1)),
+        title: Set(post.title.clone()),
+        content: Set(post.content.clone()),
+        content_format: Set(post.content_format),
+        edited_by: Set(post.updated_by),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+// Różnica między dwiema rewizjami (`from` może być brakiem poprzedniej wersji)
+pub fn diff(from: Option<&Model>, to: &Model) -> RevisionDiff {
+    RevisionDiff {
+        from: from.map_or(0, |r| r.revision),
+        to: to.revision,
+        title: diff_lines(from.map_or("", |r| &r.title), &to.title),
+        content: diff_lines(from.map_or("", |r| &r.content), &to.content),
+    }
+}
+
+// Historia od najnowszej rewizji, każda ze zmianami względem poprzedniej
+pub fn history(mut revisions: Vec<Model>) -> Vec<RevisionEntry> {
+    revisions.sort_by_key(|r| r.revision);
+    let mut entries: Vec<RevisionEntry> = revisions
+        .iter()
+        .enumerate()
+        .map(|(i, revision)| RevisionEntry {
+            changes: diff(i.checked_sub(1).map(|prev| &revisions[prev]), revision),
+            revision: revision.clone(),
+        })
+        .collect();
+    entries.reverse();
+    entries
+}
+
+// Różnica linia po linii (najdłuższy wspólny podciąg)
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Wspólny początek i koniec nie wymagają porównywania
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let line = |op, text: &str| DiffLine {
+        op,
+        text: text.to_owned(),
+    };
+    let mut lines: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|t| line(DiffOp::Equal, t))
+        .collect();
+
+    if a.len() * b.len() > MAX_DIFF_CELLS {
+        lines.extend(a.iter().map(|t| line(DiffOp::Delete, t)));
+        lines.extend(b.iter().map(|t| line(DiffOp::Insert, t)));
+    } else {
+        // lcs[i][j] — długość LCS dla a[i..] i b[j..]
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(line(DiffOp::Equal, a[i]));
+                i += 1;
+                j += 1;
+            } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(line(DiffOp::Delete, a[i]));
+                i += 1;
+            } else {
+                lines.push(line(DiffOp::Insert, b[j]));
+                j += 1;
+            }
+        }
+    }
+
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|t| line(DiffOp::Equal, t)),
+    );
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ops(lines: &[DiffLine]) -> Vec<(DiffOp, &str)> {
+        lines.iter().map(|l| (l.op, l.text.as_str())).collect()
+    }
+
+    fn revision(revision: i32, title: &str, content: &str) -> Model {
+        Model {
+            id: revision,
+            post_id: 1,
+            revision,
+            title: title.to_owned(),
+            content: content.to_owned(),
+            content_format: ContentFormat::Plain,
+            edited_by: None,
+            created_at: chrono::Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn empty_texts_have_no_lines() {
+        assert!(diff_lines("", "").is_empty());
+    }
+
+    #[test]
+    fn insert_into_empty_text() {
+        assert_eq!(
+            ops(&diff_lines("", "a\nb")),
+            vec![(DiffOp::Insert, "a"), (DiffOp::Insert, "b")]
+        );
+    }
+
+    #[test]
+    fn delete_everything() {
+        assert_eq!(
+            ops(&diff_lines("a\nb", "")),
+            vec![(DiffOp::Delete, "a"), (DiffOp::Delete, "b")]
+        );
+    }
+
+    #[test]
+    fn pure_insert_keeps_prefix_and_suffix() {
+        assert_eq!(
+            ops(&diff_lines("a\nd", "a\nb\nc\nd")),
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Insert, "b"),
+                (DiffOp::Insert, "c"),
+                (DiffOp::Equal, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pure_delete_keeps_prefix_and_suffix() {
+        assert_eq!(
+            ops(&diff_lines("a\nb\nc\nd", "a\nd")),
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "b"),
+                (DiffOp::Delete, "c"),
+                (DiffOp::Equal, "d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_texts_are_all_equal() {
+        assert_eq!(
+            ops(&diff_lines("a\nb", "a\nb")),
+            vec![(DiffOp::Equal, "a"), (DiffOp::Equal, "b")]
+        );
+    }
+
+    #[test]
+    fn replaced_middle_line() {
+        assert_eq!(
+            ops(&diff_lines("a\nb\nc", "a\nx\nc")),
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "b"),
+                (DiffOp::Insert, "x"),
+                (DiffOp::Equal, "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lcs_keeps_common_lines_between_changes() {
+        assert_eq!(
+            ops(&diff_lines("x\na\ny\nb", "a\nz\nb\nw")),
+            vec![
+                (DiffOp::Delete, "x"),
+                (DiffOp::Equal, "a"),
+                (DiffOp::Delete, "y"),
+                (DiffOp::Insert, "z"),
+                (DiffOp::Equal, "b"),
+                (DiffOp::Insert, "w"),
+            ]
+        );
+    }
+
+    #[test]
+    fn large_diff_falls_back_to_replacing_the_middle() {
+        let old: Vec<String> = (0..2001).map(|i| format!("old {}", i)).collect();
+        let mut new: Vec<String> = (0..2001).map(|i| format!("new {}", i)).collect();
+        // Wspólna linia w środku — przy pełnym LCS byłaby Equal
+        new[1000] = old[1000].clone();
+        let old = format!("head\n{}\ntail", old.join("\n"));
+        let new = format!("head\n{}\ntail", new.join("\n"));
+
+        let lines = diff_lines(&old, &new);
+        assert_eq!(lines.len(), 2 + 2001 * 2);
+        assert_eq!(ops(&lines[..1]), vec![(DiffOp::Equal, "head")]);
+        assert!(lines[1..2002].iter().all(|l| l.op == DiffOp::Delete));
+        assert!(lines[2002..4003].iter().all(|l| l.op == DiffOp::Insert));
+        assert_eq!(ops(&lines[4003..]), vec![(DiffOp::Equal, "tail")]);
+    }
+
+    #[test]
+    fn history_is_newest_first_with_changes_against_previous() {
+        let entries = history(vec![
+            revision(2, "Title", "a\nb"),
+            revision(1, "Title", "a"),
+            revision(3, "New title", "a\nb"),
+        ]);
+
+        let numbers: Vec<i32> = entries.iter().map(|e| e.revision.revision).collect();
+        assert_eq!(numbers, vec![3, 2, 1]);
+
+        assert_eq!(entries[0].changes.from, 2);
+        assert_eq!(
+            ops(&entries[0].changes.title),
+            vec![(DiffOp::Delete, "Title"), (DiffOp::Insert, "New title")]
+        );
+        assert!(entries[0]
+            .changes
+            .content
+            .iter()
+            .all(|l| l.op == DiffOp::Equal));
+
+        assert_eq!(
+            ops(&entries[1].changes.content),
+            vec![(DiffOp::Equal, "a"), (DiffOp::Insert, "b")]
+        );
+
+        // Pierwsza rewizja — względem pustego posta
+        assert_eq!(entries[2].changes.from, 0);
+        assert_eq!(
+            ops(&entries[2].changes.title),
+            vec![(DiffOp::Insert, "Title")]
+        );
+    }
+}
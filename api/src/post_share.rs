@@ -0,0 +1,112 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::post::PostResponse;
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum ShareRole {
+    // Może czytać post (i komentować/reagować)
+    #[sea_orm(string_value = "viewer")]
+    Viewer,
+    // Może też zmieniać stan todo
+    #[sea_orm(string_value = "editor")]
+    Editor,
+}
+
+// Poziom dostępu zalogowanego użytkownika do posta; kolejność wariantów ma znaczenie
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl From<ShareRole> for Access {
+    fn from(role: ShareRole) -> Self {
+        match role {
+            ShareRole::Viewer => Access::Viewer,
+            ShareRole::Editor => Access::Editor,
+        }
+    }
+}
+
+// `/todos/{id}/shares` — zaproszenie współpracownika po adresie e-mail
+#[derive(Deserialize)]
+pub struct ShareCreate {
+    pub email: String,
+    pub role: ShareRole,
+}
+
+// Post z listy "udostępnione mi"
+#[derive(Serialize)]
+pub struct SharedPost {
+    #[serde(flatten)]
+    pub post: PostResponse,
+    pub role: ShareRole,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "post_shares")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub post_id: i32,
+    pub user_id: i32,
+    pub role: ShareRole,
+    pub invited_by: i32,
+    // Brak daty — zaproszenie czeka na akceptację i nie daje jeszcze dostępu
+    pub accepted_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::post::Entity",
+        from = "Column::PostId",
+        to = "super::post::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Post,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Przyjęte udostępnienia
+pub fn find_accepted() -> Select<Entity> {
+    Entity::find().filter(Column::AcceptedAt.is_not_null())
+}
@@ -0,0 +1,323 @@
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::web;
+use actix_web::{Error, HttpResponse};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use futures_util::future::{ok, LocalBoxFuture, Ready};
+use sea_orm::entity::prelude::*;
+use sea_orm::{DatabaseTransaction, DbBackend, FromQueryResult, Statement};
+use serde::{Deserialize, Serialize};
+
+use crate::config::role_quota;
+use crate::jwt::claims_from_request;
+use crate::user;
+
+// Indywidualne limity użytkownika; None — obowiązuje limit jego roli
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "user_quotas")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub user_id: i32,
+    pub max_posts: Option<i32>,
+    pub max_attachment_bytes: Option<i64>,
+    pub max_requests_per_day: Option<i32>,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Ustawienie limitów przez admina; null przywraca limit roli, wartość ujemna znosi limit
+#[derive(Deserialize)]
+pub struct QuotaUpdate {
+    pub max_posts: Option<i32>,
+    pub max_attachment_bytes: Option<i64>,
+    pub max_requests_per_day: Option<i32>,
+}
+
+// Obowiązujące limity; None — bez limitu
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Limits {
+    pub max_posts: Option<u64>,
+    pub max_attachment_bytes: Option<u64>,
+    pub max_requests_per_day: Option<u64>,
+}
+
+// Wartość ujemna oznacza brak limitu
+pub fn limit(value: i64) -> Option<u64> {
+    u64::try_from(value).ok()
+}
+
+// Limity roli nadpisane indywidualnymi ustawieniami użytkownika
+pub async fn limits_for<C>(db: &C, user: &user::Model) -> Result<Limits, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut limits = role_quota(&user.role);
+    if let Some(custom) = Entity::find_by_id(user.id).one(db).await? {
+        if let Some(max_posts) = custom.max_posts {
+            limits.max_posts = limit(max_posts.into());
+        }
+        if let Some(max_attachment_bytes) = custom.max_attachment_bytes {
+            limits.max_attachment_bytes = limit(max_attachment_bytes);
+        }
+        if let Some(max_requests_per_day) = custom.max_requests_per_day {
+            limits.max_requests_per_day = limit(max_requests_per_day.into());
+        }
+    }
+    Ok(limits)
+}
+
+#[derive(Serialize)]
+pub struct Meter {
+    pub used: u64,
+    pub limit: Option<u64>,
+}
+
+impl Meter {
+    // Czy dołożenie `adding` przekroczy limit
+    pub fn exceeded_by(&self, adding: u64) -> bool {
+        self.limit
+            .is_some_and(|limit| self.used.saturating_add(adding) > limit)
+    }
+}
+
+// `/user/usage`: zużycie i limity konta
+#[derive(Serialize)]
+pub struct Usage {
+    pub posts: Meter,
+    pub attachment_bytes: Meter,
+    pub requests_today: Meter,
+    // Kiedy licznik żądań wraca do zera (północ UTC)
+    pub requests_reset_at: DateTime<Utc>,
+}
+
+#[derive(FromQueryResult)]
+struct UsageRow {
+    posts: i64,
+    attachment_bytes: i64,
+    requests_today: i32,
+}
+
+// Posty liczymy bez usuniętych (w koszu nie zajmują limitu); załączniki — wszystkie wgrane
+// przez użytkownika razem z ich miniaturami, bo do trwałego usunięcia posta ich pliki wciąż
+// leżą w magazynie. Miniatury powstają w tle, więc liczą się dopiero do kolejnych wgrań.
+pub async fn usage<C>(db: &C, user: &user::Model) -> Result<Usage, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let limits = limits_for(db, user).await?;
+    let row = UsageRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM posts WHERE user_id = $1 AND deleted_at IS NULL) AS posts,
+            (SELECT COALESCE(SUM(size_bytes), 0)::bigint FROM attachments WHERE user_id = $1)
+                + (SELECT COALESCE(SUM(v.size_bytes), 0)::bigint FROM attachment_variants v
+                    JOIN attachments a ON a.id = v.attachment_id WHERE a.user_id = $1)
+                AS attachment_bytes,
+            COALESCE((SELECT count FROM request_counts WHERE user_id = $1 AND day = $2), 0)
+                AS requests_today
+        "#,
+        [user.id.into(), today().into()],
+    ))
+    .one(db)
+    .await?
+    .ok_or_else(|| DbErr::RecordNotFound(String::from("usage")))?;
+
+    Ok(Usage {
+        posts: Meter {
+            used: row.posts as u64,
+            limit: limits.max_posts,
+        },
+        attachment_bytes: Meter {
+            used: row.attachment_bytes as u64,
+            limit: limits.max_attachment_bytes,
+        },
+        requests_today: Meter {
+            used: row.requests_today as u64,
+            limit: limits.max_requests_per_day,
+        },
+        requests_reset_at: next_reset(),
+    })
+}
+
+// Zużycie pod blokadą wiersza użytkownika: zapisy tego samego konta w innych transakcjach
+// czekają, aż ta się skończy, więc dwa równoległe żądania nie zmieszczą się razem
+// w ostatnim wolnym miejscu. Sprawdzenie limitu i zapis muszą być w tej samej transakcji.
+pub async fn locked_usage(txn: &DatabaseTransaction, user: &user::Model) -> Result<Usage, DbErr> {
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT id FROM users WHERE id = $1 FOR UPDATE",
+        [user.id.into()],
+    ))
+    .await?;
+    usage(txn, user).await
+}
+
+fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+fn next_reset() -> DateTime<Utc> {
+    let tomorrow = today().checked_add_days(Days::new(1)).expect("valid date");
+    tomorrow.and_hms_opt(0, 0, 0).expect("valid time").and_utc()
+}
+
+// Zwiększ dzisiejszy licznik żądań użytkownika i zwróć nową wartość
+async fn count_request(db: &DbConn, user_id: i32) -> Result<u64, DbErr> {
+    #[derive(FromQueryResult)]
+    struct Counted {
+        count: i32,
+    }
+
+    let counted = Counted::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        INSERT INTO request_counts (user_id, day, count) VALUES ($1, $2, 1)
+        ON CONFLICT (user_id, day) DO UPDATE SET count = request_counts.count + 1
+        RETURNING count
+        "#,
+        [user_id.into(), today().into()],
+    ))
+    .one(db)
+    .await?;
+    Ok(counted.map_or(0, |c| c.count as u64))
+}
+
+// Liczniki z minionych dni nie są już potrzebne
+pub async fn purge_request_counts(db: &DbConn) -> Result<(), DbErr> {
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "DELETE FROM request_counts WHERE day < $1",
+        [today().into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+// null — limit roli; do bazy trafia to, co podał admin
+pub fn active_model(user_id: i32, update: &QuotaUpdate) -> ActiveModel {
+    use sea_orm::ActiveValue::Set;
+
+    ActiveModel {
+        user_id: Set(user_id),
+        max_posts: Set(update.max_posts),
+        max_attachment_bytes: Set(update.max_attachment_bytes),
+        max_requests_per_day: Set(update.max_requests_per_day),
+        updated_at: Set(Utc::now().into()),
+    }
+}
+
+// === Request Quota Middleware ===
+
+// Dzienny limit żądań zalogowanego użytkownika; po jego przekroczeniu 429 z Retry-After
+// do północy UTC. Żądania bez poprawnego tokena nie są liczone (odrzuci je JwtMiddleware).
+#[derive(Clone)]
+pub struct RequestQuotaMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestQuotaMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = RequestQuotaMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestQuotaMiddlewareService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct RequestQuotaMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestQuotaMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let user_id = claims_from_request(req.request()).and_then(|c| c.sub.parse::<i32>().ok());
+        let db = req
+            .app_data::<web::Data<DbConn>>()
+            .map(|db| db.get_ref().clone());
+        let (user_id, db) = match (user_id, db) {
+            (Some(user_id), Some(db)) => (user_id, db),
+            _ => {
+                let fut = self.service.call(req);
+                return Box::pin(async move { Ok(fut.await?.map_into_boxed_body()) });
+            }
+        };
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            match exceeds_daily_requests(&db, user_id).await {
+                Ok(Some(limit)) => {
+                    let retry_after = (next_reset() - Utc::now()).num_seconds().max(1);
+                    let res = HttpResponse::TooManyRequests()
+                        .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                        .body(format!(
+                            "Daily request quota exceeded ({} requests per day)",
+                            limit
+                        ));
+                    return Ok(req.into_response(res));
+                }
+                Ok(None) => {}
+                // Awaria licznika nie blokuje API
+                Err(e) => eprintln!("Failed to count request: {}", e),
+            }
+            Ok(service.call(req).await?.map_into_boxed_body())
+        })
+    }
+}
+
+// Limit, który właśnie przekroczono; None — żądanie mieści się w limicie
+async fn exceeds_daily_requests(db: &DbConn, user_id: i32) -> Result<Option<u64>, DbErr> {
+    let user = match user::find_active_by_id(user_id).one(db).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    let Some(max) = limits_for(db, &user).await?.max_requests_per_day else {
+        return Ok(None);
+    };
+    let count = count_request(db, user_id).await?;
+    Ok((count > max).then_some(max))
+}
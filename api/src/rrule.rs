@@ -0,0 +1,423 @@
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+
+// Ile okresów (dni/tygodni/miesięcy/lat) maksymalnie przeglądamy szukając wystąpień
+const MAX_PERIODS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// Podzbiór RFC 5545 RRULE: FREQ, INTERVAL, COUNT, UNTIL, BYDAY (tygodniowo), BYMONTHDAY (miesięcznie)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRule {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<DateTime<Utc>>,
+    pub by_day: Vec<Weekday>,
+    pub by_month_day: Vec<i32>,
+}
+
+impl FromStr for RRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut freq = None;
+        let mut rule = RRule {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: vec![],
+            by_month_day: vec![],
+        };
+
+        for part in s.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid RRULE part: {}", part))?;
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("Unsupported FREQ: {}", other)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or("INTERVAL must be a positive number")?
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|c| *c > 0)
+                            .ok_or("COUNT must be a positive number")?,
+                    )
+                }
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|d| {
+                            d.parse::<i32>()
+                                .ok()
+                                .filter(|d| *d != 0 && (-31..=31).contains(d))
+                                .ok_or_else(|| format!("Invalid BYMONTHDAY: {}", d))
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                other => return Err(format!("Unsupported RRULE part: {}", other)),
+            }
+        }
+
+        rule.freq = freq.ok_or("RRULE must contain FREQ")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("COUNT and UNTIL cannot be used together".into());
+        }
+        if !rule.by_day.is_empty() && rule.freq != Frequency::Weekly {
+            return Err("BYDAY is only supported with FREQ=WEEKLY".into());
+        }
+        if !rule.by_month_day.is_empty() && rule.freq != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported with FREQ=MONTHLY".into());
+        }
+
+        rule.by_day.sort_by_key(|d| d.num_days_from_monday());
+        rule.by_day.dedup();
+        Ok(rule)
+    }
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday, String> {
+    match s.trim().to_ascii_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        other => Err(format!("Invalid BYDAY: {}", other)),
+    }
+}
+
+// UNTIL jako `YYYYMMDD` albo `YYYYMMDDTHHMMSSZ`
+fn parse_until(s: &str) -> Result<DateTime<Utc>, String> {
+    let s = s.trim_end_matches('Z');
+    let naive = NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(s, "%Y%m%d")
+                .map(|d| d.and_hms_opt(23, 59, 59).expect("valid time"))
+        })
+        .map_err(|_| format!("Invalid UNTIL: {}", s))?;
+    Ok(Utc.from_utc_datetime(&naive))
+}
+
+impl RRule {
+    // Kolejne wystąpienia (ściśle po `after`) serii zaczynającej się w `start` (DTSTART)
+    pub fn occurrences_after(
+        &self,
+        start: DateTime<FixedOffset>,
+        after: DateTime<FixedOffset>,
+        limit: usize,
+    ) -> Vec<DateTime<FixedOffset>> {
+        let mut result = vec![];
+        let mut index = 0u32;
+
+        for period in 0..MAX_PERIODS {
+            for candidate in self.period_candidates(start, period) {
+                if candidate < start {
+                    continue;
+                }
+                if self.until.is_some_and(|until| candidate > until) {
+                    return result;
+                }
+                index += 1;
+                if self.count.is_some_and(|count| index > count) {
+                    return result;
+                }
+                if candidate > after {
+                    result.push(candidate);
+                    if result.len() >= limit {
+                        return result;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    // Wystąpienia w n-tym okresie (w kolejności chronologicznej)
+    fn period_candidates(
+        &self,
+        start: DateTime<FixedOffset>,
+        period: u32,
+    ) -> Vec<DateTime<FixedOffset>> {
+        let local = start.naive_local();
+        let (date, time) = (local.date(), local.time());
+        let step = period.saturating_mul(self.interval);
+
+        let dates: Vec<NaiveDate> = match self.freq {
+            Frequency::Daily => date
+                .checked_add_days(Days::new(step.into()))
+                .into_iter()
+                .collect(),
+            Frequency::Weekly if self.by_day.is_empty() => date
+                .checked_add_days(Days::new(u64::from(step) * 7))
+                .into_iter()
+                .collect(),
+            Frequency::Weekly => {
+                let monday = date - Days::new(date.weekday().num_days_from_monday().into());
+                match monday.checked_add_days(Days::new(u64::from(step) * 7)) {
+                    Some(week) => self
+                        .by_day
+                        .iter()
+                        .map(|d| week + Days::new(d.num_days_from_monday().into()))
+                        .collect(),
+                    None => vec![],
+                }
+            }
+            Frequency::Monthly => {
+                let first = date.with_day(1).expect("valid day");
+                match first.checked_add_months(Months::new(step)) {
+                    Some(month) => {
+                        let days = if self.by_month_day.is_empty() {
+                            vec![date.day() as i32]
+                        } else {
+                            self.by_month_day.clone()
+                        };
+                        let mut dates: Vec<NaiveDate> = days
+                            .into_iter()
+                            .filter_map(|d| month_day(month, d))
+                            .collect();
+                        dates.sort();
+                        dates.dedup();
+                        dates
+                    }
+                    None => vec![],
+                }
+            }
+            // 29 lutego istnieje tylko w latach przestępnych — pozostałe lata pomijamy
+            Frequency::Yearly => {
+                NaiveDate::from_ymd_opt(date.year() + step as i32, date.month(), date.day())
+                    .into_iter()
+                    .collect()
+            }
+        };
+
+        dates
+            .into_iter()
+            .filter_map(|d| {
+                start
+                    .offset()
+                    .from_local_datetime(&d.and_time(time))
+                    .single()
+            })
+            .collect()
+    }
+}
+
+// Dzień miesiąca liczony od początku (1..31) albo od końca (-1 = ostatni); nieistniejące pomijamy
+fn month_day(first_of_month: NaiveDate, day: i32) -> Option<NaiveDate> {
+    if day > 0 {
+        first_of_month.with_day(day as u32)
+    } else {
+        let next_month = first_of_month.checked_add_months(Months::new(1))?;
+        let last = next_month.pred_opt()?;
+        let target = last.day() as i32 + day + 1;
+        if target < 1 {
+            None
+        } else {
+            last.with_day(target as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).expect("valid test date")
+    }
+
+    fn dates(rule: &str, start: &str, limit: usize) -> Vec<String> {
+        let start = at(start);
+        rule.parse::<RRule>()
+            .expect("valid rule")
+            .occurrences_after(start, start - chrono::Duration::seconds(1), limit)
+            .iter()
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parses_all_parts() {
+        let rule: RRule = "RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=5;BYDAY=FR,MO,MO"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.freq, Frequency::Weekly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.count, Some(5));
+        assert_eq!(rule.by_day, vec![Weekday::Mon, Weekday::Fri]);
+
+        let rule: RRule = "freq=daily;until=20260131".parse().unwrap();
+        assert_eq!(
+            rule.until,
+            Some(Utc.with_ymd_and_hms(2026, 1, 31, 23, 59, 59).unwrap())
+        );
+        let rule: RRule = "FREQ=DAILY;UNTIL=20260131T120000Z".parse().unwrap();
+        assert_eq!(
+            rule.until,
+            Some(Utc.with_ymd_and_hms(2026, 1, 31, 12, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=x",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;COUNT=3;UNTIL=20260101",
+            "FREQ=DAILY;UNTIL=2026-01-01",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=MONTHLY;BYMONTHDAY=0",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;BYSETPOS=1",
+            "FREQ",
+        ] {
+            assert!(rule.parse::<RRule>().is_err(), "accepted {:?}", rule);
+        }
+    }
+
+    #[test]
+    fn daily_interval_and_count() {
+        assert_eq!(
+            dates(
+                "FREQ=DAILY;INTERVAL=3;COUNT=3",
+                "2026-02-27T09:00:00+01:00",
+                10
+            ),
+            ["2026-02-27", "2026-03-02", "2026-03-05"]
+        );
+    }
+
+    #[test]
+    fn weekly_by_day_starts_at_dtstart() {
+        // 2026-01-07 to środa — poniedziałek tego tygodnia jest przed DTSTART
+        assert_eq!(
+            dates("FREQ=WEEKLY;BYDAY=MO,WE,FR", "2026-01-07T08:00:00Z", 5),
+            [
+                "2026-01-07",
+                "2026-01-09",
+                "2026-01-12",
+                "2026-01-14",
+                "2026-01-16"
+            ]
+        );
+        assert_eq!(
+            dates("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU", "2026-01-06T08:00:00Z", 3),
+            ["2026-01-06", "2026-01-20", "2026-02-03"]
+        );
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        assert_eq!(
+            dates("FREQ=DAILY;UNTIL=20260103", "2026-01-01T10:00:00Z", 10),
+            ["2026-01-01", "2026-01-02", "2026-01-03"]
+        );
+        assert_eq!(
+            dates(
+                "FREQ=DAILY;UNTIL=20260103T090000Z",
+                "2026-01-01T10:00:00Z",
+                10
+            ),
+            ["2026-01-01", "2026-01-02"]
+        );
+    }
+
+    #[test]
+    fn monthly_skips_missing_days() {
+        assert_eq!(
+            dates("FREQ=MONTHLY", "2026-01-31T10:00:00Z", 4),
+            ["2026-01-31", "2026-03-31", "2026-05-31", "2026-07-31"]
+        );
+    }
+
+    #[test]
+    fn monthly_last_day_and_leap_years() {
+        assert_eq!(
+            dates("FREQ=MONTHLY;BYMONTHDAY=-1", "2028-01-31T10:00:00Z", 3),
+            ["2028-01-31", "2028-02-29", "2028-03-31"]
+        );
+        assert_eq!(
+            dates("FREQ=MONTHLY;BYMONTHDAY=-1", "2027-02-28T10:00:00Z", 1),
+            ["2027-02-28"]
+        );
+        assert_eq!(
+            dates("FREQ=MONTHLY;BYMONTHDAY=15,1", "2026-01-01T10:00:00Z", 4),
+            ["2026-01-01", "2026-01-15", "2026-02-01", "2026-02-15"]
+        );
+    }
+
+    #[test]
+    fn yearly_on_leap_day() {
+        assert_eq!(
+            dates("FREQ=YEARLY;COUNT=3", "2024-02-29T10:00:00Z", 10),
+            ["2024-02-29", "2028-02-29", "2032-02-29"]
+        );
+    }
+
+    #[test]
+    fn occurrences_are_strictly_after() {
+        let rule: RRule = "FREQ=DAILY".parse().unwrap();
+        let start = at("2026-01-01T10:00:00+02:00");
+        let next = rule.occurrences_after(start, start, 1);
+        assert_eq!(next, [at("2026-01-02T10:00:00+02:00")]);
+    }
+
+    #[test]
+    fn count_includes_past_occurrences() {
+        let rule: RRule = "FREQ=DAILY;COUNT=3".parse().unwrap();
+        let start = at("2026-01-01T10:00:00Z");
+        assert_eq!(
+            rule.occurrences_after(start, at("2026-01-02T10:00:00Z"), 10),
+            [at("2026-01-03T10:00:00Z")]
+        );
+        assert!(rule
+            .occurrences_after(start, at("2026-01-03T10:00:00Z"), 10)
+            .is_empty());
+    }
+}
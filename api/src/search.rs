@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{DbBackend, DbConn, DbErr, FromQueryResult, Statement, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::organization::Tenant;
+use crate::search_index;
+
+// Fragmenty pasujące do zapytania oznaczamy znacznikiem <mark>; poza nim tekst jest
+// zawsze escapowany, więc podświetlenie można wstawić do HTML bez ryzyka XSS
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>";
+const SNIPPET_OPTIONS: &str =
+    "StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15, MaxFragments=2";
+
+// Znaki specjalne HTML zamienione na encje (te same w obu backendach wyszukiwania)
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// To samo co `escape_html`, ale w SQL — ts_headline dostaje już escapowany tekst
+// i dokłada tylko znaczniki <mark>
+fn sql_escape_html(column: &str) -> String {
+    [
+        ("<", "&lt;"),
+        (">", "&gt;"),
+        ("\"", "&quot;"),
+        ("''", "&#39;"),
+    ]
+    .iter()
+    .fold(
+        format!("replace({}, '&', '&amp;')", column),
+        |sql, (from, to)| format!("replace({}, '{}', '{}')", sql, from, to),
+    )
+}
+
+// Gdzie szukamy: pełnotekstowo w PostgreSQL albo we wbudowanym indeksie na dysku
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchBackend {
+    Postgres,
+    Tantivy,
+}
+
+impl FromStr for SearchBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "postgres" => Ok(SearchBackend::Postgres),
+            "tantivy" => Ok(SearchBackend::Tantivy),
+            other => Err(format!("Unknown search backend: {}", other)),
+        }
+    }
+}
+
+// `/search?q="lista zakupów" mle*&author=3`
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub author: Option<i32>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct SearchHit {
+    pub id: i32,
+    pub user_id: i32,
+    pub title: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub rank: f32,
+    // Tytuł i fragment treści z podświetlonymi trafieniami
+    pub title_highlight: String,
+    pub snippet: String,
+}
+
+// Jeden warunek zapytania; wszystkie muszą być spełnione (AND)
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueryTerm {
+    Word(String),
+    // słowo* — dowolne słowo zaczynające się od prefiksu
+    Prefix(String),
+    // "dwa słowa" — słowa występujące kolejno po sobie
+    Phrase(Vec<String>),
+}
+
+// Rozbij zapytanie użytkownika na warunki. Znaki spoza liter i cyfr są separatorami,
+// więc żaden backend nie dostaje od użytkownika surowej składni zapytań.
+pub fn parse_query(input: &str) -> Vec<QueryTerm> {
+    let mut terms = vec![];
+
+    for (i, part) in input.split('"').enumerate() {
+        // Nieparzyste fragmenty leżą między cudzysłowami
+        if i % 2 == 1 {
+            let mut words = lexemes(part);
+            match words.len() {
+                0 => {}
+                1 => terms.push(QueryTerm::Word(words.remove(0))),
+                _ => terms.push(QueryTerm::Phrase(words)),
+            }
+            continue;
+        }
+
+        for word in part.split_whitespace() {
+            let mut words = lexemes(word);
+            // Gwiazdka dotyczy tylko ostatniego członu, np. "e-mai*" → e & mai*
+            let prefix = if word.ends_with('*') {
+                words.pop()
+            } else {
+                None
+            };
+            terms.extend(words.into_iter().map(QueryTerm::Word));
+            terms.extend(prefix.map(QueryTerm::Prefix));
+        }
+    }
+    terms
+}
+
+fn lexemes(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Składnia `to_tsquery`: fraza → (dwa <-> słowa), prefiks → słowo:*, warunki łączy &
+fn to_tsquery(terms: &[QueryTerm]) -> String {
+    terms
+        .iter()
+        .map(|term| match term {
+            QueryTerm::Word(word) => word.clone(),
+            QueryTerm::Prefix(prefix) => format!("{}:*", prefix),
+            QueryTerm::Phrase(words) => format!("({})", words.join(" <-> ")),
+        })
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+// Posty publiczne i własne z aktywnej organizacji `viewer`, od najlepiej pasujących.
+// Pytamy wbudowany indeks, jeśli działa, a w przeciwnym razie PostgreSQL.
+pub async fn search_posts(
+    db: &DbConn,
+    terms: &[QueryTerm],
+    viewer: Tenant,
+    author: Option<i32>,
+    limit: u64,
+    offset: u64,
+) -> Result<Vec<SearchHit>, DbErr> {
+    match search_index::get() {
+        Some(index) => index.search(db, terms, viewer, author, limit, offset).await,
+        None => search_postgres(db, terms, viewer, author, limit, offset).await,
+    }
+}
+
+async fn search_postgres(
+    db: &DbConn,
+    terms: &[QueryTerm],
+    viewer: Tenant,
+    author: Option<i32>,
+    limit: u64,
+    offset: u64,
+) -> Result<Vec<SearchHit>, DbErr> {
+    let mut values: Vec<Value> = vec![
+        to_tsquery(terms).into(),
+        HEADLINE_OPTIONS.into(),
+        SNIPPET_OPTIONS.into(),
+        (limit as i64).into(),
+        (offset as i64).into(),
+        viewer.user_id.into(),
+        viewer.org_id.into(),
+    ];
+
+    let author_filter = match author {
+        Some(author) => {
+            values.push(author.into());
+            "AND p.user_id = $8"
+        }
+        None => "",
+    };
+
+    let sql = format!(
+        r#"SELECT p.id, p.user_id, p.title, p.created_at,
+                ts_rank(p.search_vector, q) AS rank,
+                ts_headline('simple', {}, q, $2) AS title_highlight,
+                ts_headline('simple', {}, q, $3) AS snippet
+            FROM posts p
+            JOIN users u ON u.id = p.user_id
+            CROSS JOIN to_tsquery('simple', $1) q
+            WHERE p.search_vector @@ q
+                AND p.deleted_at IS NULL
+                AND u.deleted_at IS NULL
+                AND p.org_id = $7
+                AND ((p.visibility = 'public' AND NOT p.draft) OR p.user_id = $6)
+                {}
+            ORDER BY rank DESC, p.created_at DESC
+            LIMIT $4 OFFSET $5"#,
+        sql_escape_html("p.title"),
+        sql_escape_html("p.content"),
+        author_filter
+    );
+
+    SearchHit::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        sql,
+        values,
+    ))
+    .all(db)
+    .await
+}
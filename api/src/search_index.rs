@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use actix_web::web;
+use sea_orm::{ColumnTrait, DbConn, DbErr, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+use sea_orm::{JoinType, QuerySelect, RelationTrait};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, BoostQuery, Occur, PhraseQuery, Query, RegexQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, INDEXED, STORED, STRING, TEXT};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::TextAnalyzer;
+use tantivy::{
+    doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, TantivyError, Term,
+};
+
+use crate::organization::Tenant;
+use crate::post::{self, Visibility};
+use crate::search::{escape_html, QueryTerm, SearchHit};
+use crate::user;
+
+// Pamięć na bufor zapisu indeksu (tantivy wymaga co najmniej 15 MB na wątek)
+const WRITER_MEMORY: usize = 50_000_000;
+// Ile postów wczytujemy naraz przy przebudowie indeksu
+const REBUILD_BATCH: u64 = 1000;
+// Długość fragmentu treści w wynikach
+const SNIPPET_CHARS: usize = 200;
+// Trafienie w tytule liczy się bardziej niż w treści (jak waga 'A' w PostgreSQL)
+const TITLE_BOOST: f32 = 2.0;
+// Ile trafień naraz bierzemy z indeksu do sprawdzenia w bazie
+const SEARCH_BATCH: usize = 100;
+
+static INDEX: OnceLock<PostIndex> = OnceLock::new();
+
+#[derive(Clone, Copy)]
+struct Fields {
+    id: Field,
+    user_id: Field,
+    org_id: Field,
+    visibility: Field,
+    title: Field,
+    content: Field,
+}
+
+// Wbudowany indeks odwrócony postów. Przechowuje tylko id — tytuł, treść i to, czy post
+// jest jeszcze aktywny, zawsze bierzemy z bazy, więc nieaktualny wpis nie wycieknie w wynikach.
+pub struct PostIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    fields: Fields,
+}
+
+// Otwórz (albo utwórz) indeks w katalogu i używaj go do wyszukiwania
+pub fn init(dir: &Path) -> tantivy::Result<&'static PostIndex> {
+    if let Some(index) = INDEX.get() {
+        return Ok(index);
+    }
+    let index = PostIndex::open(dir)?;
+    Ok(INDEX.get_or_init(|| index))
+}
+
+// Indeks, jeśli konfiguracja go włączyła
+pub fn get() -> Option<&'static PostIndex> {
+    INDEX.get()
+}
+
+// Zaktualizuj wpisy postów po zatwierdzonym zapisie (usunięty post wypada z indeksu);
+// błąd tylko logujemy — indeks można przebudować
+pub async fn sync_posts(posts: Vec<post::Model>) {
+    if let Some(index) = get() {
+        apply(move || index.update(&posts, &[])).await;
+    }
+}
+
+// Odśwież wpisy postów o podanych id według stanu w bazie — tych, których już nie ma, usuń
+pub async fn sync_post_ids(db: &DbConn, ids: Vec<i32>) {
+    let Some(index) = get() else { return };
+    if ids.is_empty() {
+        return;
+    }
+    let posts = match post::Entity::find()
+        .filter(post::Column::Id.is_in(ids.clone()))
+        .all(db)
+        .await
+    {
+        Ok(posts) => posts,
+        Err(e) => return eprintln!("Failed to update search index: {}", e),
+    };
+    let gone: Vec<Term> = ids
+        .into_iter()
+        .filter(|id| !posts.iter().any(|p| p.id == *id))
+        .map(|id| Term::from_field_i64(index.fields.id, id.into()))
+        .collect();
+    apply(move || index.update(&posts, &gone)).await;
+}
+
+// Usuń z indeksu wszystkie posty użytkownika (usunięcie konta)
+pub async fn remove_user_posts(user_ids: Vec<i32>) {
+    let Some(index) = get() else { return };
+    if user_ids.is_empty() {
+        return;
+    }
+    let terms: Vec<Term> = user_ids
+        .into_iter()
+        .map(|id| Term::from_field_i64(index.fields.user_id, id.into()))
+        .collect();
+    apply(move || index.update(&[], &terms)).await;
+}
+
+async fn apply<F>(update: F)
+where
+    F: FnOnce() -> tantivy::Result<()> + Send + 'static,
+{
+    match web::block(update).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => eprintln!("Failed to update search index: {}", e),
+        Err(e) => eprintln!("Failed to update search index: {}", e),
+    }
+}
+
+fn schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        id: builder.add_i64_field("id", INDEXED | STORED),
+        user_id: builder.add_i64_field("user_id", INDEXED),
+        org_id: builder.add_i64_field("org_id", INDEXED),
+        visibility: builder.add_text_field("visibility", STRING),
+        title: builder.add_text_field("title", TEXT),
+        content: builder.add_text_field("content", TEXT),
+    };
+    (builder.build(), fields)
+}
+
+fn visibility_value(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Private => "private",
+        Visibility::Unlisted => "unlisted",
+        Visibility::Public => "public",
+    }
+}
+
+fn db_err(e: impl std::fmt::Display) -> DbErr {
+    DbErr::Custom(format!("Search index error: {}", e))
+}
+
+impl PostIndex {
+    fn open(dir: &Path) -> tantivy::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let (schema, fields) = schema();
+        let index = match Index::open_or_create(MmapDirectory::open(dir)?, schema.clone()) {
+            Ok(index) => index,
+            // Indeks z inną wersją schematu zakładamy od nowa — zostanie przebudowany z bazy.
+            // Usuwamy tylko katalog, który na pewno jest indeksem (ma meta.json) — nigdy
+            // przypadkowy katalog wskazany przez SEARCH_INDEX_DIR
+            Err(TantivyError::SchemaError(e)) => {
+                if !dir.join("meta.json").is_file() {
+                    return Err(TantivyError::SchemaError(format!(
+                        "{} is not a search index directory: {}",
+                        dir.display(),
+                        e
+                    )));
+                }
+                eprintln!("Search index schema changed ({}), recreating it", e);
+                fs::remove_dir_all(dir)?;
+                fs::create_dir_all(dir)?;
+                Index::create_in_dir(dir, schema)?
+            }
+            Err(e) => return Err(e),
+        };
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+
+        Ok(PostIndex {
+            index,
+            reader,
+            writer: Mutex::new(writer),
+            fields,
+        })
+    }
+
+    pub fn num_docs(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
+    fn document(&self, post: &post::Model) -> TantivyDocument {
+        doc!(
+            self.fields.id => i64::from(post.id),
+            self.fields.user_id => i64::from(post.user_id),
+            self.fields.org_id => i64::from(post.org_id),
+            // Szkic nie jest publiczny, dopóki nie zostanie opublikowany
+            self.fields.visibility => visibility_value(if post.draft {
+                Visibility::Private
+            } else {
+                post.visibility
+            }),
+            self.fields.title => post.title.as_str(),
+            self.fields.content => post.content.as_str(),
+        )
+    }
+
+    // Usuń wpisy pasujące do `removed`, a posty z `posts` zapisz od nowa — o ile nie są usunięte
+    fn update(&self, posts: &[post::Model], removed: &[Term]) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock().expect("search index writer poisoned");
+        for term in removed {
+            writer.delete_term(term.clone());
+        }
+        for post in posts {
+            writer.delete_term(Term::from_field_i64(self.fields.id, post.id.into()));
+            if post.deleted_at.is_none() {
+                writer.add_document(self.document(post))?;
+            }
+        }
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    // Zbuduj indeks od zera ze wszystkich nieusuniętych postów w bazie
+    pub async fn rebuild(&self, db: &DbConn) -> Result<u64, DbErr> {
+        let mut pages = post::find_active()
+            .order_by_asc(post::Column::Id)
+            .paginate(db, REBUILD_BATCH);
+
+        self.writer
+            .lock()
+            .expect("search index writer poisoned")
+            .delete_all_documents()
+            .map_err(db_err)?;
+
+        let mut count = 0;
+        while let Some(posts) = pages.fetch_and_next().await? {
+            let writer = self.writer.lock().expect("search index writer poisoned");
+            for post in &posts {
+                writer.add_document(self.document(post)).map_err(db_err)?;
+            }
+            count += posts.len() as u64;
+        }
+
+        let mut writer = self.writer.lock().expect("search index writer poisoned");
+        writer.commit().map_err(db_err)?;
+        self.reader.reload().map_err(db_err)?;
+        Ok(count)
+    }
+
+    pub async fn search(
+        &'static self,
+        db: &DbConn,
+        terms: &[QueryTerm],
+        viewer: Tenant,
+        author: Option<i32>,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<SearchHit>, DbErr> {
+        let query = self.query(terms, viewer, author).map_err(db_err)?;
+
+        // Wpis w indeksie może być nieaktualny albo niewidoczny dla `viewer`, więc stronicujemy
+        // dopiero po sprawdzeniu trafień w bazie — dobieramy kolejne, aż strona się zapełni
+        let wanted = (offset + limit) as usize;
+        let batch = wanted.max(SEARCH_BATCH);
+        let mut hits: Vec<(f32, post::Model)> = vec![];
+        let mut scanned = 0;
+        loop {
+            let query = query.box_clone();
+            let scored = web::block(move || self.top_ids(query, batch, scanned))
+                .await
+                .map_err(db_err)?
+                .map_err(db_err)?;
+            let fetched = scored.len();
+            scanned += fetched;
+
+            // Tylko aktywne posty aktywnych użytkowników, nadal widoczne dla `viewer` w jego organizacji
+            let ids: Vec<i32> = scored.iter().map(|(_, id)| *id).collect();
+            let mut posts: HashMap<i32, post::Model> = post::find_active()
+                .join(JoinType::InnerJoin, post::Relation::User.def())
+                .filter(user::Column::DeletedAt.is_null())
+                .filter(post::Column::OrgId.eq(viewer.org_id))
+                .filter(post::listed_for(Some(viewer.user_id)))
+                .filter(post::Column::Id.is_in(ids))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|p| (p.id, p))
+                .collect();
+            hits.extend(
+                scored
+                    .into_iter()
+                    .filter_map(|(rank, id)| posts.remove(&id).map(|post| (rank, post))),
+            );
+
+            if hits.len() >= wanted || fetched < batch {
+                break;
+            }
+        }
+
+        let title_tokenizer = self
+            .index
+            .tokenizer_for_field(self.fields.title)
+            .map_err(db_err)?;
+        let content_tokenizer = self
+            .index
+            .tokenizer_for_field(self.fields.content)
+            .map_err(db_err)?;
+
+        Ok(hits
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|(rank, post)| SearchHit {
+                id: post.id,
+                user_id: post.user_id,
+                title: post.title.clone(),
+                created_at: post.created_at,
+                rank,
+                title_highlight: highlight(
+                    &title_tokenizer,
+                    self.fields.title,
+                    terms,
+                    &post.title,
+                    post.title.len().max(1),
+                ),
+                snippet: highlight(
+                    &content_tokenizer,
+                    self.fields.content,
+                    terms,
+                    &post.content,
+                    SNIPPET_CHARS,
+                ),
+            })
+            .collect())
+    }
+
+    fn top_ids(
+        &self,
+        query: Box<dyn Query>,
+        limit: usize,
+        offset: usize,
+    ) -> tantivy::Result<Vec<(f32, i32)>> {
+        let searcher = self.reader.searcher();
+        let top = searcher.search(&query, &TopDocs::with_limit(limit).and_offset(offset))?;
+
+        let mut ids = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            if let Some(id) = doc.get_first(self.fields.id).and_then(|v| v.as_i64()) {
+                ids.push((score, id as i32));
+            }
+        }
+        Ok(ids)
+    }
+
+    // Każdy warunek musi pasować do tytułu albo treści; post leży w organizacji `viewer`
+    // i jest publiczny albo należy do niego
+    fn query(
+        &self,
+        terms: &[QueryTerm],
+        viewer: Tenant,
+        author: Option<i32>,
+    ) -> tantivy::Result<Box<dyn Query>> {
+        let org = Term::from_field_i64(self.fields.org_id, viewer.org_id.into());
+        let public =
+            Term::from_field_text(self.fields.visibility, visibility_value(Visibility::Public));
+        let own = Term::from_field_i64(self.fields.user_id, viewer.user_id.into());
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(org, IndexRecordOption::Basic)),
+            ),
+            (
+                Occur::Must,
+                Box::new(BooleanQuery::new(vec![
+                    (
+                        Occur::Should,
+                        Box::new(TermQuery::new(public, IndexRecordOption::Basic)),
+                    ),
+                    (
+                        Occur::Should,
+                        Box::new(TermQuery::new(own, IndexRecordOption::Basic)),
+                    ),
+                ])),
+            ),
+        ];
+
+        for term in terms {
+            let mut either: Vec<(Occur, Box<dyn Query>)> = vec![];
+            for field in [self.fields.title, self.fields.content] {
+                let query = field_query(field, term)?;
+                let query: Box<dyn Query> = if field == self.fields.title {
+                    Box::new(BoostQuery::new(query, TITLE_BOOST))
+                } else {
+                    query
+                };
+                either.push((Occur::Should, query));
+            }
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(either))));
+        }
+
+        if let Some(author) = author {
+            let term = Term::from_field_i64(self.fields.user_id, author.into());
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+}
+
+fn field_query(field: Field, term: &QueryTerm) -> tantivy::Result<Box<dyn Query>> {
+    Ok(match term {
+        QueryTerm::Word(word) => Box::new(TermQuery::new(
+            Term::from_field_text(field, word),
+            IndexRecordOption::WithFreqs,
+        )),
+        // Słowa zawierają tylko litery i cyfry, więc można je wstawić do wyrażenia wprost
+        QueryTerm::Prefix(prefix) => {
+            Box::new(RegexQuery::from_pattern(&format!("{}.*", prefix), field)?)
+        }
+        QueryTerm::Phrase(words) => Box::new(PhraseQuery::new(
+            words
+                .iter()
+                .map(|word| Term::from_field_text(field, word))
+                .collect(),
+        )),
+    })
+}
+
+// Fragment tekstu z trafieniami w <mark>; bez trafień — początek tekstu
+fn highlight(
+    tokenizer: &TextAnalyzer,
+    field: Field,
+    terms: &[QueryTerm],
+    text: &str,
+    max_chars: usize,
+) -> String {
+    let mut words: BTreeMap<String, f32> = BTreeMap::new();
+    for term in terms {
+        match term {
+            QueryTerm::Word(word) => {
+                words.insert(word.clone(), 1.0);
+            }
+            QueryTerm::Phrase(phrase) => {
+                words.extend(phrase.iter().map(|word| (word.clone(), 1.0)));
+            }
+            // Prefiks rozwijamy do słów, które faktycznie występują w tekście
+            QueryTerm::Prefix(prefix) => {
+                let mut tokenizer = tokenizer.clone();
+                let mut stream = tokenizer.token_stream(text);
+                while stream.advance() {
+                    let token = &stream.token().text;
+                    if token.starts_with(prefix.as_str()) {
+                        words.insert(token.clone(), 1.0);
+                    }
+                }
+            }
+        }
+    }
+
+    let generator = SnippetGenerator::new(words, tokenizer.clone(), field, max_chars);
+    let mut snippet = generator.snippet(text);
+    if snippet.is_empty() {
+        return escape_html(&text.chars().take(max_chars).collect::<String>());
+    }
+    snippet.set_snippet_prefix_postfix("<mark>", "</mark>");
+    snippet.to_html()
+}
@@ -0,0 +1,96 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{LikeExpr, Query, SelectStatement};
+use serde::{Deserialize, Serialize};
+
+// `/todos/tags?prefix=wo` — podpowiedzi tagów zalogowanego użytkownika
+#[derive(Deserialize)]
+pub struct TagQuery {
+    pub prefix: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "tags")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+    #[sea_orm(has_many = "super::post_tag::Entity")]
+    PostTag,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::post_tag::Relation::Post.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::post_tag::Relation::Tag.def().rev())
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+// Najdłuższy dopuszczalny tag (w znakach)
+const MAX_TAG_LEN: usize = 50;
+
+// Tagi porównujemy bez wielkości liter i białych znaków na brzegach; za długi tag to błąd
+pub fn normalize(names: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized = vec![];
+    for name in names {
+        let name = name.trim().to_lowercase();
+        if name.chars().count() > MAX_TAG_LEN {
+            return Err(format!(
+                "Tags can be at most {} characters long",
+                MAX_TAG_LEN
+            ));
+        }
+        if !name.is_empty() {
+            normalized.push(name);
+        }
+    }
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+// Wzorzec LIKE dla podpowiedzi: `%` i `_` z prefiksu to zwykłe znaki, nie symbole wieloznaczne
+pub fn prefix_pattern(prefix: &str) -> LikeExpr {
+    let escaped = prefix
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    LikeExpr::new(format!("{}%", escaped)).escape('\\')
+}
+
+// Id tagów przypiętych do postów organizacji — tagi z innych organizacji nie wyciekają
+pub fn used_in_org(org_id: i32) -> SelectStatement {
+    Query::select()
+        .column((super::post_tag::Entity, super::post_tag::Column::TagId))
+        .from(super::post_tag::Entity)
+        .inner_join(
+            super::post::Entity,
+            Expr::col((super::post::Entity, super::post::Column::Id))
+                .equals((super::post_tag::Entity, super::post_tag::Column::PostId)),
+        )
+        .and_where(Expr::col((super::post::Entity, super::post::Column::OrgId)).eq(org_id))
+        .to_owned()
+}
@@ -0,0 +1,534 @@
+use std::io::Cursor;
+
+use actix_web::web::{self, Bytes};
+use chrono::Utc;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, Limits};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+
+use crate::attachment::{self, VariantsStatus};
+use crate::attachment_variant;
+use crate::storage::Storage;
+
+// Ile obrazków przetwarza jeden przebieg zadania w tle
+const BATCH_SIZE: u64 = 10;
+
+// Jakość JPEG dla miniatur i obróconych oryginałów
+const JPEG_QUALITY: u8 = 85;
+
+// Granice dekodowania — mały plik może deklarować gigantyczny obraz (bomba dekompresyjna)
+const MAX_IMAGE_SIDE: u32 = 12_000;
+const MAX_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+
+// Generowane warianty: nazwa i najdłuższy bok w pikselach (mniejszych obrazków nie powiększamy)
+pub const VARIANTS: [(&str, u32); 2] = [("thumb", 200), ("preview", 800)];
+
+// Formaty, które umiemy odczytać
+pub fn is_supported(content_type: &str) -> bool {
+    matches!(
+        content_type,
+        "image/jpeg" | "image/png" | "image/gif" | "image/webp"
+    )
+}
+
+// Oryginał obrazka oczyszczony przy wgraniu, zanim ktokolwiek może go pobrać
+pub enum Cleaned {
+    // Nie było metadanych do usunięcia
+    Unchanged,
+    Stripped(Vec<u8>),
+    // Formatu nie umiemy oczyścić albo plik jest uszkodzony — takiego pliku nie przyjmujemy
+    Unsupported,
+}
+
+// Usuwa metadane bez dekodowania obrazu, więc mieści się w obsłudze żądania. GIF nie ma
+// bloku EXIF; pozostałych formatów (HEIC, TIFF…) nie umiemy czyścić.
+pub fn clean_upload(data: &[u8], content_type: &str) -> Cleaned {
+    let stripped = match content_type {
+        "image/jpeg" => strip_jpeg(data),
+        "image/png" => strip_png(data),
+        "image/webp" => strip_webp(data),
+        "image/gif" => return Cleaned::Unchanged,
+        _ => return Cleaned::Unsupported,
+    };
+    match stripped {
+        Some(stripped) if stripped.len() != data.len() => Cleaned::Stripped(stripped),
+        Some(_) => Cleaned::Unchanged,
+        None => Cleaned::Unsupported,
+    }
+}
+
+pub struct Rendered {
+    pub variant: &'static str,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct Processed {
+    // Oryginał bez EXIF — None, gdy nie było czego usuwać
+    pub original: Option<Vec<u8>>,
+    pub variants: Vec<Rendered>,
+}
+
+// Miniatury i oryginał bez metadanych. Miniatury są kodowane od nowa, więc nie niosą EXIF;
+// oryginał tracimy bezstratnie (wycinając segmenty z metadanymi), chyba że EXIF obracał obraz —
+// wtedy zapisujemy go już obróconego, bo bez EXIF wyświetliłby się krzywo.
+pub fn process(data: &[u8]) -> ImageResult<Processed> {
+    let format = image::guess_format(data)?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let original = match format {
+        ImageFormat::Jpeg | ImageFormat::Png if orientation != Orientation::NoTransforms => {
+            Some(encode(&image, format == ImageFormat::Png)?.2)
+        }
+        _ => strip_metadata(data, format),
+    };
+
+    let mut variants = vec![];
+    for (variant, max_side) in VARIANTS {
+        let resized = if image.width() > max_side || image.height() > max_side {
+            image.thumbnail(max_side, max_side)
+        } else {
+            image.clone()
+        };
+        let (content_type, extension, data) = encode(&resized, image.color().has_alpha())?;
+        variants.push(Rendered {
+            variant,
+            content_type,
+            extension,
+            width: resized.width(),
+            height: resized.height(),
+            data,
+        });
+    }
+
+    Ok(Processed { original, variants })
+}
+
+// Obrazki z przezroczystością jako PNG, pozostałe jako JPEG
+fn encode(image: &DynamicImage, png: bool) -> ImageResult<(&'static str, &'static str, Vec<u8>)> {
+    let mut data = vec![];
+    if png {
+        image.write_with_encoder(PngEncoder::new(&mut data))?;
+        Ok(("image/png", "png", data))
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY))?;
+        Ok(("image/jpeg", "jpg", data))
+    }
+}
+
+// Plik bez segmentów z metadanymi (EXIF, XMP, IPTC); None, gdy nic nie usunięto
+// albo format jest nieznany lub uszkodzony
+pub fn strip_metadata(data: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    let stripped = match format {
+        ImageFormat::Jpeg => strip_jpeg(data),
+        ImageFormat::Png => strip_png(data),
+        ImageFormat::WebP => strip_webp(data),
+        _ => None,
+    }?;
+    (stripped.len() != data.len()).then_some(stripped)
+}
+
+// Segmenty JPEG aż do początku danych obrazu (SOS); pomijamy APP1 (EXIF, XMP) i APP13 (IPTC)
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut i = 2;
+    loop {
+        if *data.get(i)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(i + 1)?;
+        match marker {
+            // Bajty wypełnienia przed znacznikiem
+            0xFF => i += 1,
+            // Początek skanu albo koniec obrazu — reszta pliku bez zmian
+            0xDA | 0xD9 => {
+                out.extend_from_slice(&data[i..]);
+                return Some(out);
+            }
+            // Znaczniki bez długości
+            0x01 | 0xD0..=0xD7 => {
+                out.extend_from_slice(&data[i..i + 2]);
+                i += 2;
+            }
+            _ => {
+                let len = u16::from_be_bytes([*data.get(i + 2)?, *data.get(i + 3)?]) as usize;
+                let end = i + 2 + len;
+                let segment = data.get(i..end)?;
+                if marker != 0xE1 && marker != 0xED {
+                    out.extend_from_slice(segment);
+                }
+                i = end;
+            }
+        }
+    }
+}
+
+// Fragmenty PNG: pomijamy eXIf i tekstowe (tEXt, zTXt, iTXt — w nich bywa XMP)
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    if !data.starts_with(&SIGNATURE) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&SIGNATURE);
+    let mut i = SIGNATURE.len();
+    while i < data.len() {
+        let len = u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?) as usize;
+        let kind = data.get(i + 4..i + 8)?;
+        // długość + typ + dane + CRC
+        let end = i + 12 + len;
+        let chunk = data.get(i..end)?;
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt") {
+            out.extend_from_slice(chunk);
+        }
+        i = end;
+    }
+    Some(out)
+}
+
+// Fragmenty RIFF/WebP: pomijamy EXIF i XMP, a w nagłówku VP8X gasimy flagi ich obecności
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    if data.get(..4)? != b"RIFF" || data.get(8..12)? != b"WEBP" {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..12]);
+    let mut i = 12;
+    while i < data.len() {
+        let kind = data.get(i..i + 4)?;
+        let len = u32::from_le_bytes(data.get(i + 4..i + 8)?.try_into().ok()?) as usize;
+        // Dane fragmentu są wyrównane do parzystej długości
+        let end = (i + 8 + len + (len & 1)).min(data.len());
+        let chunk = data.get(i..end)?;
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" if chunk.len() > 8 => {
+                let start = out.len();
+                out.extend_from_slice(chunk);
+                out[start + 8] &= !(0x08 | 0x04);
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+        i = end;
+    }
+    let riff_size = u32::try_from(out.len() - 8).ok()?;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
+
+// Przetwórz oczekujące obrazki: zapisz warianty obok oryginału i podmień oryginał na wersję
+// bez metadanych. Plik, którego nie da się odczytać, oznaczamy jako "failed".
+pub async fn process_pending(
+    db: &DatabaseConnection,
+    storage: &dyn Storage,
+) -> Result<usize, DbErr> {
+    let pending = attachment::Entity::find()
+        .filter(attachment::Column::VariantsStatus.eq(VariantsStatus::Pending))
+        .order_by_asc(attachment::Column::Id)
+        .limit(BATCH_SIZE)
+        .all(db)
+        .await?;
+
+    let count = pending.len();
+    for file in pending {
+        let status = match process_one(db, storage, &file).await {
+            Ok(()) => VariantsStatus::Ready,
+            Err(e) => {
+                eprintln!("Failed to process image attachment {}: {}", file.id, e);
+                VariantsStatus::Failed
+            }
+        };
+        let mut active = file.into_active_model();
+        active.variants_status = Set(Some(status));
+        // Załącznik mógł zostać usunięty w trakcie przetwarzania
+        match active.update(db).await {
+            Ok(_) | Err(DbErr::RecordNotUpdated) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(count)
+}
+
+async fn process_one(
+    db: &DatabaseConnection,
+    storage: &dyn Storage,
+    file: &attachment::Model,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let data = storage
+        .get(&file.storage_key)
+        .await?
+        .ok_or("Attachment file is missing")?;
+    // Dekodowanie i skalowanie obciąża CPU — poza wątkami serwera
+    let processed = web::block(move || process(&data)).await??;
+
+    for rendered in processed.variants {
+        let storage_key = format!(
+            "{}.{}.{}",
+            file.storage_key, rendered.variant, rendered.extension
+        );
+        let size_bytes = rendered.data.len() as i64;
+        storage
+            .put(
+                &storage_key,
+                Bytes::from(rendered.data),
+                rendered.content_type,
+            )
+            .await?;
+
+        let row = attachment_variant::ActiveModel {
+            attachment_id: Set(file.id),
+            variant: Set(rendered.variant.to_owned()),
+            content_type: Set(rendered.content_type.to_owned()),
+            width: Set(rendered.width as i32),
+            height: Set(rendered.height as i32),
+            size_bytes: Set(size_bytes),
+            storage_key: Set(storage_key),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        };
+        attachment_variant::Entity::insert(row)
+            .on_conflict(
+                OnConflict::columns([
+                    attachment_variant::Column::AttachmentId,
+                    attachment_variant::Column::Variant,
+                ])
+                .update_columns([
+                    attachment_variant::Column::ContentType,
+                    attachment_variant::Column::Width,
+                    attachment_variant::Column::Height,
+                    attachment_variant::Column::SizeBytes,
+                    attachment_variant::Column::StorageKey,
+                ])
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+    }
+
+    if let Some(original) = processed.original {
+        let size_bytes = original.len() as i64;
+        storage
+            .put(&file.storage_key, Bytes::from(original), &file.content_type)
+            .await?;
+        attachment::Entity::update_many()
+            .col_expr(
+                attachment::Column::SizeBytes,
+                sea_orm::sea_query::Expr::value(size_bytes),
+            )
+            .filter(attachment::Column::Id.eq(file.id))
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn png_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = (payload.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(payload);
+        // CRC nie jest sprawdzany przy wycinaniu
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn webp_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn webp(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+        data.extend_from_slice(b"WEBP");
+        data.extend_from_slice(&body);
+        data
+    }
+
+    const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    const SCAN: [u8; 6] = [0xFF, 0xDA, 0x12, 0x34, 0xFF, 0xD9];
+
+    #[test]
+    fn strip_jpeg_drops_exif_and_iptc() {
+        let app0 = jpeg_segment(0xE0, b"JFIF\0");
+        let dqt = jpeg_segment(0xDB, &[1, 2, 3]);
+        let data = [
+            &[0xFF, 0xD8][..],
+            &app0,
+            &jpeg_segment(0xE1, b"Exif\0\0GPS"),
+            // Bajt wypełnienia przed znacznikiem
+            &[0xFF],
+            &jpeg_segment(0xED, b"Photoshop 3.0"),
+            &dqt,
+            &SCAN,
+        ]
+        .concat();
+
+        let stripped = strip_jpeg(&data).unwrap();
+        assert_eq!(stripped, [&[0xFF, 0xD8][..], &app0, &dqt, &SCAN].concat());
+        assert_eq!(strip_metadata(&stripped, ImageFormat::Jpeg), None);
+    }
+
+    #[test]
+    fn strip_jpeg_rejects_truncated_segments() {
+        let data = [
+            &[0xFF, 0xD8][..],
+            &jpeg_segment(0xE1, b"Exif\0\0GPS"),
+            &SCAN,
+        ]
+        .concat();
+        // Ucięte w środku segmentu EXIF i w środku jego długości
+        assert_eq!(strip_jpeg(&data[..8]), None);
+        assert_eq!(strip_jpeg(&data[..5]), None);
+        // Bez znacznika początku skanu
+        assert_eq!(strip_jpeg(&data[..data.len() - SCAN.len()]), None);
+        assert_eq!(strip_jpeg(b"not a jpeg"), None);
+    }
+
+    #[test]
+    fn strip_png_drops_exif_and_text_chunks() {
+        let ihdr = png_chunk(b"IHDR", &[0; 13]);
+        let idat = png_chunk(b"IDAT", &[1, 2, 3]);
+        let iend = png_chunk(b"IEND", &[]);
+        let data = [
+            &PNG_SIGNATURE[..],
+            &ihdr,
+            &png_chunk(b"eXIf", b"MM\0*GPS"),
+            &png_chunk(b"tEXt", b"Comment\0secret"),
+            &png_chunk(b"iTXt", b"XML:com.adobe.xmp\0"),
+            &png_chunk(b"zTXt", b"Author\0\0x"),
+            &idat,
+            &iend,
+        ]
+        .concat();
+
+        let stripped = strip_png(&data).unwrap();
+        assert_eq!(stripped, [&PNG_SIGNATURE[..], &ihdr, &idat, &iend].concat());
+        assert_eq!(strip_metadata(&stripped, ImageFormat::Png), None);
+    }
+
+    #[test]
+    fn strip_png_rejects_truncated_chunks() {
+        let data = [
+            &PNG_SIGNATURE[..],
+            &png_chunk(b"IHDR", &[0; 13]),
+            &png_chunk(b"eXIf", b"MM\0*GPS"),
+        ]
+        .concat();
+        assert_eq!(strip_png(&data[..data.len() - 3]), None);
+        assert_eq!(strip_png(&data[..PNG_SIGNATURE.len() + 6]), None);
+        assert_eq!(strip_png(b"\x89PNX"), None);
+    }
+
+    #[test]
+    fn strip_webp_drops_metadata_and_clears_vp8x_flags() {
+        // Flagi: alfa (0x10), EXIF (0x08), XMP (0x04)
+        let vp8x = webp_chunk(b"VP8X", &[0x1C, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let vp8l = webp_chunk(b"VP8L", &[7, 8, 9]);
+        let data = webp(&[
+            vp8x,
+            vp8l.clone(),
+            webp_chunk(b"EXIF", b"MM\0*GPS"),
+            webp_chunk(b"XMP ", b"<x:xmpmeta/>"),
+        ]);
+
+        let stripped = strip_webp(&data).unwrap();
+        let expected = webp(&[
+            webp_chunk(b"VP8X", &[0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            vp8l,
+        ]);
+        assert_eq!(stripped, expected);
+        // Rozmiar RIFF odpowiada nowej długości pliku
+        let riff_size = u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_size, stripped.len() - 8);
+        assert_eq!(strip_metadata(&stripped, ImageFormat::WebP), None);
+    }
+
+    #[test]
+    fn strip_webp_handles_truncated_chunks() {
+        let data = webp(&[
+            webp_chunk(b"VP8L", &[7, 8, 9]),
+            webp_chunk(b"EXIF", b"MM\0*GPS"),
+        ]);
+        // Ucięte dane ostatniego fragmentu — EXIF i tak znika
+        let stripped = strip_webp(&data[..data.len() - 2]).unwrap();
+        assert_eq!(stripped, webp(&[webp_chunk(b"VP8L", &[7, 8, 9])]));
+        // Ucięty nagłówek fragmentu — pliku nie da się przejść
+        assert_eq!(strip_webp(&data[..12 + 12 + 6]), None);
+        assert_eq!(strip_webp(b"RIFF\0\0\0\0WEBX"), None);
+    }
+
+    #[test]
+    fn clean_upload_refuses_formats_it_cannot_strip() {
+        assert!(matches!(
+            clean_upload(b"II*\0", "image/tiff"),
+            Cleaned::Unsupported
+        ));
+        assert!(matches!(
+            clean_upload(b"\xFF\xD8\xFF", "image/jpeg"),
+            Cleaned::Unsupported
+        ));
+        assert!(matches!(
+            clean_upload(b"GIF89a", "image/gif"),
+            Cleaned::Unchanged
+        ));
+    }
+
+    #[test]
+    fn process_rejects_images_over_the_size_limit() {
+        let image = DynamicImage::new_luma8(MAX_IMAGE_SIDE + 1, 1);
+        let mut data = vec![];
+        image
+            .write_with_encoder(PngEncoder::new(&mut data))
+            .unwrap();
+        assert!(process(&data).is_err());
+
+        let image = DynamicImage::new_luma8(300, 10);
+        let mut data = vec![];
+        image
+            .write_with_encoder(PngEncoder::new(&mut data))
+            .unwrap();
+        let processed = process(&data).unwrap();
+        assert_eq!(processed.variants[0].width, 200);
+    }
+}
@@ -1,4 +1,5 @@
 use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::{self, Set};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -10,6 +11,23 @@ pub struct UserCreate {
     pub password: String,
 }
 
+// Dane logowania (np. do przywrócenia usuniętego konta)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[sea_orm(string_value = "user")]
+    User,
+    #[sea_orm(string_value = "admin")]
+    Admin,
+}
+
 #[derive(Clone, Serialize, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "users")]
 pub struct Model {
@@ -19,7 +37,17 @@ pub struct Model {
     pub lastname: String,
     pub age: i32,
     pub email: String,
+    // Hash nigdy nie trafia do odpowiedzi ani do logu audytu
+    #[serde(skip_serializing)]
     pub password: String,
+    pub role: Role,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    pub created_by: Option<i32>,
+    pub updated_by: Option<i32>,
+    // Numer wersji wiersza (ETag), rośnie przy każdym zapisie
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -34,5 +62,29 @@ impl Related<super::post::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+// Znaczniki czasu ustawiane automatycznie przy każdym zapisie
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        if insert {
+            self.created_at = Set(now);
+        } else if let ActiveValue::Unchanged(version) | ActiveValue::Set(version) = self.version {
+            self.version = Set(version + 1);
+        }
+        self.updated_at = Set(now);
+        Ok(self)
+    }
+}
+
+// Użytkownicy, którzy nie usunęli konta
+pub fn find_active() -> Select<Entity> {
+    Entity::find().filter(Column::DeletedAt.is_null())
+}
 
+pub fn find_active_by_id(id: i32) -> Select<Entity> {
+    Entity::find_by_id(id).filter(Column::DeletedAt.is_null())
+}
@@ -0,0 +1,79 @@
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse};
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, TransactionTrait,
+};
+
+use crate::config::require_if_match;
+
+// Wersja wiersza jako ETag, np. "3"
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+// Sprawdź If-Match względem bieżącej wersji zasobu. Zwraca odpowiedź, jeśli zapis
+// trzeba odrzucić: 412 przy innej wersji, 428 gdy nagłówek jest wymagany, a go brak.
+pub fn precondition_failed(req: &HttpRequest, version: i32) -> Option<HttpResponse> {
+    let if_match = match req
+        .headers()
+        .get(header::IF_MATCH)
+        .and_then(|h| h.to_str().ok())
+    {
+        Some(if_match) => if_match,
+        None if require_if_match() => {
+            return Some(HttpResponse::PreconditionRequired().body("If-Match header is required"))
+        }
+        None => return None,
+    };
+
+    // Lista ETagów po przecinku albo `*`; If-Match porównuje ETagi w trybie silnym
+    let current = etag(version);
+    if if_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == current)
+    {
+        return None;
+    }
+    Some(
+        HttpResponse::PreconditionFailed()
+            .insert_header((header::ETAG, current))
+            .body("Resource has been modified"),
+    )
+}
+
+// Ktoś zapisał wiersz między naszym odczytem a zapisem
+pub fn is_conflict(e: &DbErr) -> bool {
+    matches!(e, DbErr::RecordNotUpdated)
+}
+
+// Zapis z optymistyczną blokadą: UPDATE trafia tylko w wiersz w wersji, którą wczytaliśmy
+// (nową wersję ustawia before_save). Jeśli ktoś zapisał wiersz w międzyczasie,
+// dostajemy DbErr::RecordNotUpdated — patrz `is_conflict`. UPDATE i to, co zapisuje
+// after_save (np. rewizja posta), idą w jednej transakcji.
+pub async fn update_versioned<A, C>(
+    active: A,
+    db: &C,
+    version: <A::Entity as EntityTrait>::Column,
+) -> Result<<A::Entity as EntityTrait>::Model, DbErr>
+where
+    A: ActiveModelTrait + ActiveModelBehavior + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    C: ConnectionTrait + TransactionTrait,
+{
+    let loaded = active
+        .get(version)
+        .into_value()
+        .ok_or_else(|| DbErr::Custom("Row version was not loaded".to_owned()))?;
+
+    let txn = db.begin().await?;
+    let active = active.before_save(&txn, false).await?;
+    let model = A::Entity::update(active)
+        .filter(version.eq(loaded))
+        .exec(&txn)
+        .await?;
+    let model = A::after_save(model, &txn, false).await?;
+    txn.commit().await?;
+    Ok(model)
+}